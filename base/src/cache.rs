@@ -2,66 +2,128 @@
 //! Any module that interacts with the file system must go through this module first.
 //!
 //! The purpose of this module is to make sure access to the file system is limited to only
-//! the `.ibis` cache directory (or wherever the user specifies using environment variables).
+//! the cache directory. By default that's `~/.ibis`, but it can be moved with `--cache-dir`, the
+//! `IBIS_HOME` environment variable, or the XDG base directory variables (see `cache_path()`).
 //! All functions in the module are scoped to the `.ibis` directory and can't access anything *above*
 //! it. This makes uninstallation easy and limits the amount of malicious activity packages could
-//! create.
+//! create. Every path is checked with `CachePath` first, which rejects absolute paths, `..`, and
+//! symbolic links that lead out of the cache directory.
 //!
-//! Having all file operations go through this module also means swapping out fs APIs is simple.
-//! For instance, right now all functions use the `tokio::fs::File` API, but if we wanted to swap
-//! that out with something faster, it could be done very easily.
+//! Files are written atomically: they're written to a temporary file next to the destination, then
+//! renamed into place, so a crash never leaves a half-written file behind. Anything that changes the
+//! cache should also hold the `CacheLock` from `CacheStore::lock()` so parallel Ibis processes don't
+//! step on each other.
+//!
+//! All file operations go through the `CacheStore` trait, so the file system can be swapped out. `FsStore`
+//! keeps everything in the cache directory, and `MemoryStore` keeps everything in memory for tests and
+//! embedded uses that shouldn't touch `$HOME`.
+
+mod path;
+mod lock;
+mod store;
+mod filesystem;
+mod memory;
+pub mod fetch;
+pub mod maintenance;
+
+pub use path::{CachePath, CachePathError};
+pub use store::{CacheEntry, CacheLock, CacheStore};
+pub use filesystem::{FsStore, write_atomic};
+pub use memory::MemoryStore;
 
+use std::env;
 use std::path::PathBuf;
-use anyhow::{Context, Result};
-use tokio::{fs::File, fs};
-use crate::constants::CACHE_PATH;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::{Context, Result, anyhow};
+use tokio::fs;
+use crate::constants::{CACHE_DIR_ENV, CACHE_PATH, XDG_DIR_NAME};
+
+/// The cache directory given on the command line, if any. Set once at startup by `set_cache_dir()`.
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Overrides the cache directory for the rest of the process, ex. from the `--cache-dir` flag.
+/// Takes priority over every environment variable. Fails if it has already been set.
+pub fn set_cache_dir(path: PathBuf) -> Result<()> {
+    CACHE_DIR.set(path).map_err(|_| anyhow!("The cache directory has already been set."))
+}
+
+/// Gets an environment variable as a directory, ignoring it if it's unset or empty. If `absolute`
+/// is true, relative paths are ignored too, as the XDG spec requires.
+fn env_dir(name: &str, absolute: bool) -> Option<PathBuf> {
+    env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .filter(|path| !absolute || path.is_absolute())
+}
+
+/// Picks between the XDG directory and `~/.ibis`. An existing `~/.ibis` keeps being used, so installed
+/// packages, config, and registries don't disappear when the XDG variables are set later, unless the XDG
+/// directory has been created too.
+fn choose_root(xdg: Option<PathBuf>, legacy: Option<PathBuf>) -> Option<PathBuf> {
+    match (xdg, legacy) {
+        (Some(xdg), Some(legacy)) if legacy.is_dir() && !xdg.exists() => Some(legacy),
+        (Some(xdg), _) => Some(xdg),
+        (None, legacy) => legacy,
+    }
+}
 
-/// Utility function for getting the cache path (place where everything in Ibis is stored).
-/// Fails if the operating system or user doesn't have a home directory set.
-async fn cache_path() -> Result<PathBuf> {
-    let mut root = dirs::home_dir().context("Unable to get home directory.")?;
-    root.push(CACHE_PATH);
-    Ok(root)
+/// Utility function for getting the cache path (place where everything in Ibis is stored). Every
+/// module goes through this, so the first of these that's set is used everywhere:
+///
+/// 1. The directory given to `set_cache_dir()` (the `--cache-dir` flag).
+/// 2. `$IBIS_HOME`.
+/// 3. `~/.ibis`, if it already exists and the XDG directory below doesn't.
+/// 4. `$XDG_DATA_HOME/ibis`.
+/// 5. `$XDG_CACHE_HOME/ibis`.
+/// 6. `~/.ibis`.
+///
+/// Fails if none of them are set and the operating system or user doesn't have a home directory set.
+fn cache_path() -> Result<PathBuf> {
+    if let Some(path) = CACHE_DIR.get() {
+        return Ok(path.clone());
+    }
+    if let Some(path) = env_dir(CACHE_DIR_ENV, false) {
+        return Ok(path);
+    }
+    let xdg = env_dir("XDG_DATA_HOME", true)
+        .or_else(|| env_dir("XDG_CACHE_HOME", true))
+        .map(|path| path.join(XDG_DIR_NAME));
+    let legacy = dirs::home_dir().map(|home| home.join(CACHE_PATH));
+    choose_root(xdg, legacy).context("Unable to get home directory.")
 }
 
-/// Creates the cache path if it doesn't exist. If it does, just returns the cache
-/// path.
+/// Creates the cache path (and any missing parent directories) if it doesn't exist. If it does, just
+/// returns the cache path.
 pub async fn get_cache_path() -> Result<PathBuf> {
-    let path = cache_path().await.context("Could not get path to cache.")?;
+    let path = cache_path().context("Could not get path to cache.")?;
     if !path.exists() {
-        fs::create_dir(&path).await.context("Could not create cache directory.")?;
-        println!(".ibis directory not found. Created .ibis directory.");
+        fs::create_dir_all(&path).await.context("Could not create cache directory.")?;
+        eprintln!("Cache directory not found. Created {}.", path.display());
     }
     Ok(path)
 }
 
-/// Gets a given path starting from the cache root. Fails if the operating system or user
-/// doesn't have a home directory set.
-pub async fn get_path(p: &str) -> Result<PathBuf> {
-    let mut path = get_cache_path().await.context("Could not get cache path.")?;
-    path.push(&p);
-    Ok(path)
+/// The current time in seconds since the Unix epoch, which is how times are stored in the cache.
+pub fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0)
 }
 
-/// Creates a directory in the cache directory starting from the cache root.
-/// Creates the cache directory if it does not already exist.
-pub async fn create_dir(path: &str) -> Result<()> {
-    let mut cache_path = get_cache_path().await.context("Could not get cache path.")?;
-    cache_path.push(path);
-    fs::create_dir(&path).await.context("Could not create directory.")?;
-    Ok(())
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
 
-/// Creates a file in the cache directory starting from the cache root.
-/// Creates the cache directory if it does not already exist.
-/// Returns the `File` struct that is created.
-pub async fn create_file(path: &str) -> Result<File> {
-    let mut cache_path = get_cache_path().await.context("Could not get cache path.")?;
-    cache_path.push(path);
-    File::create(cache_path).await.context("Could not create file>")
-}
+    #[test]
+    fn keeps_using_an_existing_home_directory() {
+        let dir = TempDir::new().unwrap();
+        let (xdg, legacy) = (dir.path().join("xdg/ibis"), dir.path().join(".ibis"));
+        assert_eq!(choose_root(Some(xdg.clone()), Some(legacy.clone())), Some(xdg.clone()));
 
-/// 
-pub async fn open_file(path: &str) -> Result<File> {
-    File::open(get_path(path).await.context("Could not get path")?).await.context("Could not open file")
-}
\ No newline at end of file
+        std::fs::create_dir(&legacy).unwrap();
+        assert_eq!(choose_root(Some(xdg.clone()), Some(legacy.clone())), Some(legacy.clone()));
+        std::fs::create_dir_all(&xdg).unwrap();
+        assert_eq!(choose_root(Some(xdg.clone()), Some(legacy.clone())), Some(xdg));
+        assert_eq!(choose_root(None, Some(legacy.clone())), Some(legacy));
+    }
+}
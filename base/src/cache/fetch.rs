@@ -0,0 +1,229 @@
+use std::time::Duration;
+use anyhow::{Context, Result, anyhow};
+use reqwest::{Response, StatusCode};
+use reqwest::header::{ETAG, HeaderName, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+use super::{CachePath, CacheStore, unix_time};
+
+/// How long to wait for a connection to the server before giving up.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a whole request, including downloading the file, can take before giving up.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// HTTP metadata about a downloaded file, stored next to it in `<path>.meta.json` so it can be revalidated
+/// with a conditional request instead of downloaded again.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Metadata {
+    /// The `ETag` header the server sent with the file, if any.
+    pub etag: Option<String>,
+    /// The `Last-Modified` header the server sent with the file, if any.
+    pub last_modified: Option<String>,
+    /// When the file was last downloaded or revalidated, in seconds since the Unix epoch.
+    pub fetched_at: u64,
+    /// When the last attempt to revalidate the file failed, if it did, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failed_at: Option<u64>,
+}
+
+impl Metadata {
+    /// When the server was last asked for the file, whether or not it answered.
+    fn checked_at(&self) -> u64 {
+        self.fetched_at.max(self.failed_at.unwrap_or(0))
+    }
+}
+
+/// What `fetch()` did to a cached file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Refreshed {
+    /// The file was fetched, or fetching it failed, less than the TTL ago, so the server wasn't asked.
+    Fresh,
+    /// The server said the file hasn't changed.
+    NotModified,
+    /// A new copy of the file was downloaded.
+    Updated,
+}
+
+fn metadata_path(path: &CachePath) -> Result<CachePath> {
+    Ok(CachePath::new(&format!("{}.meta.json", path))?)
+}
+
+/// Reads the metadata stored for a cached file. Returns None if there isn't any or it can't be read.
+pub async fn read_metadata(store: &dyn CacheStore, path: &CachePath) -> Option<Metadata> {
+    let buffer = store.read(&metadata_path(path).ok()?).await.ok()??;
+    serde_json::from_slice(&buffer).ok()
+}
+
+fn header(response: &Response, name: HeaderName) -> Option<String> {
+    response.headers().get(name).and_then(|value| value.to_str().ok()).map(String::from)
+}
+
+/// Makes sure the cached copy of `url` at `path` in the store is up to date. If the file
+/// was fetched less than `ttl` ago, does nothing unless `force` is true. Otherwise asks the server for it,
+/// sending the stored `ETag` and `Last-Modified` so the file is only downloaded again if it's changed.
+/// Fails if the server can't be reached in time or doesn't send the file; the cached copy is left as it
+/// was, and the failed attempt is recorded so the server isn't asked again until `ttl` has passed.
+/// Holds the cache lock the whole time, so parallel processes don't download the same file twice.
+pub async fn fetch(store: &dyn CacheStore, path: &CachePath, url: &str, ttl: Duration, force: bool) -> Result<Refreshed> {
+    let _lock = store.lock().await?;
+    let cached = store.exists(path).await?;
+    let metadata = if cached { read_metadata(store, path).await } else { None };
+
+    if let Some(metadata) = &metadata {
+        if !force && unix_time().saturating_sub(metadata.checked_at()) < ttl.as_secs() {
+            return Ok(Refreshed::Fresh);
+        }
+    }
+
+    let result = revalidate(store, path, url, metadata.clone()).await;
+    if let (Err(_), Some(metadata)) = (&result, metadata) {
+        let failed = Metadata { failed_at: Some(unix_time()), ..metadata };
+        store.write(&metadata_path(path)?, &serde_json::to_vec(&failed)?).await?;
+    }
+    result
+}
+
+/// Asks the server for a file, sending the validators in `metadata` if there are any, and stores it if
+/// it's changed.
+async fn revalidate(store: &dyn CacheStore, path: &CachePath, url: &str, metadata: Option<Metadata>) -> Result<Refreshed> {
+    let client = reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .context("Could not set up the HTTP client.")?;
+    let mut request = client.get(url);
+    if let Some(metadata) = &metadata {
+        if let Some(etag) = &metadata.etag {
+            request = request.header(IF_NONE_MATCH, etag.as_str());
+        }
+        if let Some(last_modified) = &metadata.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified.as_str());
+        }
+    }
+    let response = request.send().await.context(format!("Could not reach {}", url))?;
+
+    let etag = header(&response, ETAG);
+    let last_modified = header(&response, LAST_MODIFIED);
+    if let (StatusCode::NOT_MODIFIED, Some(old)) = (response.status(), metadata) {
+        // 304 responses don't have to repeat the validators, so keep the old ones if they're left out.
+        let metadata = Metadata {
+            etag: etag.or(old.etag),
+            last_modified: last_modified.or(old.last_modified),
+            fetched_at: unix_time(),
+            failed_at: None,
+        };
+        store.write(&metadata_path(path)?, &serde_json::to_vec(&metadata)?).await?;
+        return Ok(Refreshed::NotModified);
+    }
+    if !response.status().is_success() {
+        return Err(anyhow!("{} responded with {}.", url, response.status()));
+    }
+
+    let metadata = Metadata { etag, last_modified, fetched_at: unix_time(), failed_at: None };
+    let body = response.bytes().await.context(format!("Could not download {}.", url))?;
+    store.write(path, &body).await?;
+    store.write(&metadata_path(path)?, &serde_json::to_vec(&metadata)?).await?;
+    Ok(Refreshed::Updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use crate::cache::MemoryStore;
+
+    const DAY: Duration = Duration::from_secs(60 * 60 * 24);
+
+    /// Serves one canned response on a local port, and returns the URL to request and the request the
+    /// server got, once it's been answered.
+    fn serve(response: &'static str) -> (String, Arc<Mutex<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/schema.min.json", listener.local_addr().unwrap());
+        let request = Arc::new(Mutex::new(String::new()));
+        let received = request.clone();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buffer = Vec::new();
+            let mut chunk = [0; 1024];
+            while !buffer.ends_with(b"\r\n\r\n") {
+                let read = stream.read(&mut chunk).unwrap();
+                if read == 0 { break; }
+                buffer.extend_from_slice(&chunk[..read]);
+            }
+            *received.lock().unwrap() = String::from_utf8_lossy(&buffer).to_lowercase();
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+        (url, request)
+    }
+
+    /// A URL nothing is listening on.
+    fn unreachable() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}/schema.min.json", listener.local_addr().unwrap())
+    }
+
+    /// A store with a cached copy of a file, fetched `age` seconds ago with the ETag `"old"`.
+    async fn cached(age: u64) -> (MemoryStore, CachePath) {
+        let (store, path) = (MemoryStore::new(), CachePath::new("schema.min.json").unwrap());
+        store.write(&path, b"old").await.unwrap();
+        let metadata = Metadata { etag: Some(String::from("\"old\"")), fetched_at: unix_time() - age, ..Metadata::default() };
+        store.write(&metadata_path(&path).unwrap(), &serde_json::to_vec(&metadata).unwrap()).await.unwrap();
+        (store, path)
+    }
+
+    #[tokio::test]
+    async fn skips_fresh_files() {
+        let (store, path) = cached(60).await;
+        assert_eq!(fetch(&store, &path, &unreachable(), DAY, false).await.unwrap(), Refreshed::Fresh);
+        assert_eq!(store.read(&path).await.unwrap(), Some(b"old".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn keeps_files_that_have_not_changed() {
+        let (store, path) = cached(2 * DAY.as_secs()).await;
+        let (url, request) = serve("HTTP/1.1 304 Not Modified\r\nContent-Length: 0\r\n\r\n");
+
+        assert_eq!(fetch(&store, &path, &url, DAY, false).await.unwrap(), Refreshed::NotModified);
+        assert!(request.lock().unwrap().contains("if-none-match: \"old\""));
+        assert_eq!(store.read(&path).await.unwrap(), Some(b"old".to_vec()));
+        let metadata = read_metadata(&store, &path).await.unwrap();
+        assert_eq!(metadata.etag.as_deref(), Some("\"old\""));
+        assert!(unix_time() - metadata.fetched_at < 60);
+    }
+
+    #[tokio::test]
+    async fn replaces_files_that_have_changed() {
+        let (store, path) = cached(2 * DAY.as_secs()).await;
+        let (url, _) = serve("HTTP/1.1 200 OK\r\nETag: \"new\"\r\nContent-Length: 3\r\n\r\nnew");
+
+        assert_eq!(fetch(&store, &path, &url, DAY, false).await.unwrap(), Refreshed::Updated);
+        assert_eq!(store.read(&path).await.unwrap(), Some(b"new".to_vec()));
+        assert_eq!(read_metadata(&store, &path).await.unwrap().etag.as_deref(), Some("\"new\""));
+    }
+
+    #[tokio::test]
+    async fn keeps_the_cached_copy_when_the_server_is_unreachable() {
+        let (store, path) = cached(2 * DAY.as_secs()).await;
+        let url = unreachable();
+
+        assert!(fetch(&store, &path, &url, DAY, false).await.is_err());
+        assert_eq!(store.read(&path).await.unwrap(), Some(b"old".to_vec()));
+        let metadata = read_metadata(&store, &path).await.unwrap();
+        assert_eq!((metadata.etag.as_deref(), metadata.failed_at.is_some()), (Some("\"old\""), true));
+
+        // The failed attempt counts towards the TTL, so the server isn't asked again right away.
+        assert_eq!(fetch(&store, &path, &url, DAY, false).await.unwrap(), Refreshed::Fresh);
+        assert!(fetch(&store, &path, &url, DAY, true).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn records_error_responses_as_failures() {
+        let (store, path) = cached(2 * DAY.as_secs()).await;
+        let (url, _) = serve("HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\r\n");
+
+        assert!(fetch(&store, &path, &url, DAY, false).await.unwrap_err().to_string().contains("500"));
+        assert_eq!(store.read(&path).await.unwrap(), Some(b"old".to_vec()));
+        assert!(read_metadata(&store, &path).await.unwrap().failed_at.is_some());
+    }
+}
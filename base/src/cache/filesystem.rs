@@ -0,0 +1,165 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use async_trait::async_trait;
+use anyhow::{Context, Result};
+use tokio::{fs::File, fs};
+use tokio::prelude::*;
+use crate::constants::LOCK_PATH;
+use super::{CacheEntry, CacheLock, CachePath, CacheStore, get_cache_path, lock::lock_file};
+
+/// Counts temporary files, so tasks in the same process never pick the same name.
+static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Writes a file by writing a temporary file next to it and renaming that into place, so the file is
+/// never left half written. Keeps the permissions of the file it replaces. The parent directory must
+/// already exist.
+pub async fn write_atomic(destination: &Path, contents: &[u8]) -> Result<()> {
+    let name = destination.file_name().context("Could not get file name.")?.to_string_lossy();
+    let temp = destination.with_file_name(format!(
+        ".{}.{}-{}.tmp", name, std::process::id(), TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let written = async {
+        let mut file = File::create(&temp).await.context(format!("Could not create {}.", temp.display()))?;
+        file.write_all(contents).await.context(format!("Could not write {}.", temp.display()))?;
+        file.sync_all().await.context(format!("Could not write {}.", temp.display()))?;
+        if let Ok(metadata) = fs::metadata(destination).await {
+            fs::set_permissions(&temp, metadata.permissions()).await.context(format!("Could not write {}.", temp.display()))?;
+        }
+        fs::rename(&temp, destination).await.context(format!("Could not replace {}.", destination.display()))
+    }.await;
+    if written.is_err() {
+        fs::remove_file(&temp).await.ok();
+    }
+    written
+}
+
+/// A store that keeps everything in a directory on disk. By default that's the cache directory picked
+/// by `get_cache_path()`, which is created the first time it's used.
+#[derive(Debug, Clone, Default)]
+pub struct FsStore {
+    root: Option<PathBuf>,
+}
+
+impl FsStore {
+    /// A store in the cache directory picked by `get_cache_path()`.
+    pub fn new() -> FsStore {
+        FsStore::default()
+    }
+
+    /// A store in some other directory, which is created the first time it's used.
+    pub fn at(root: PathBuf) -> FsStore {
+        FsStore { root: Some(root) }
+    }
+
+    /// Gets the root of the store, creating it if it doesn't exist.
+    pub async fn root(&self) -> Result<PathBuf> {
+        match &self.root {
+            Some(root) => {
+                fs::create_dir_all(root).await.context("Could not create cache directory.")?;
+                Ok(root.clone())
+            }
+            None => get_cache_path().await,
+        }
+    }
+
+    /// Gets where a path is on disk. Fails if the path goes through a symbolic link that leaves the store.
+    pub async fn path(&self, path: &CachePath) -> Result<PathBuf> {
+        let root = self.root().await.context("Could not get cache path.")?;
+        Ok(path.resolve(&root)?)
+    }
+}
+
+#[async_trait]
+impl CacheStore for FsStore {
+    async fn read(&self, path: &CachePath) -> Result<Option<Vec<u8>>> {
+        let file_path = self.path(path).await?;
+        if !file_path.exists() {
+            return Ok(None);
+        }
+        let mut file = File::open(&file_path).await.context(format!("Could not open {}.", path))?;
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer).await.context(format!("Could not read {}.", path))?;
+        Ok(Some(buffer))
+    }
+
+    /// Writes to a temporary file next to the destination, then renames it into place. Creates any
+    /// missing parent directories.
+    async fn write(&self, path: &CachePath, contents: &[u8]) -> Result<()> {
+        let destination = self.path(path).await?;
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).await.context(format!("Could not create the directory for {}.", path))?;
+        }
+        write_atomic(&destination, contents).await.context(format!("Could not write {}.", path))
+    }
+
+    async fn exists(&self, path: &CachePath) -> Result<bool> {
+        Ok(self.path(path).await?.exists())
+    }
+
+    async fn remove(&self, path: &CachePath) -> Result<()> {
+        let file_path = self.path(path).await?;
+        let metadata = match fs::symlink_metadata(&file_path).await {
+            Ok(metadata) => metadata,
+            Err(_) => return Ok(()),
+        };
+        if metadata.is_dir() {
+            fs::remove_dir_all(&file_path).await.context(format!("Could not remove {}.", path))
+        }
+        else {
+            fs::remove_file(&file_path).await.context(format!("Could not remove {}.", path))
+        }
+    }
+
+    async fn local_path(&self, path: &CachePath) -> Result<PathBuf> {
+        self.path(path).await
+    }
+
+    /// Walks the store without following symbolic links, which are listed as files.
+    async fn list(&self) -> Result<Vec<CacheEntry>> {
+        let root = self.root().await?;
+        let mut entries = Vec::new();
+        let mut dirs = vec![root.clone()];
+        while let Some(dir) = dirs.pop() {
+            let mut read_dir = fs::read_dir(&dir).await.context(format!("Could not read {}.", dir.display()))?;
+            while let Some(entry) = read_dir.next_entry().await.context(format!("Could not read {}.", dir.display()))? {
+                let path = entry.path();
+                let metadata = fs::symlink_metadata(&path).await.context(format!("Could not read {}.", path.display()))?;
+                if metadata.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                let relative = path.strip_prefix(&root).context("Could not list the cache.")?;
+                entries.push(CacheEntry { path: CachePath::new(&relative.to_string_lossy())?, size: metadata.len() });
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Locks a file in the store that's shared with every other Ibis process.
+    async fn lock(&self) -> Result<CacheLock> {
+        let path = self.path(&CachePath::new(LOCK_PATH)?).await?;
+        lock_file(path).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn writes_atomically_into_new_directories() {
+        let root = TempDir::new().unwrap();
+        let store = FsStore::at(root.path().to_path_buf());
+        let path = CachePath::new("packages/foo/ibis.json").unwrap();
+
+        store.write(&path, b"{}").await.unwrap();
+        assert_eq!(store.read(&path).await.unwrap(), Some(b"{}".to_vec()));
+        let leftovers: Vec<_> = std::fs::read_dir(root.path().join("packages/foo")).unwrap().collect();
+        assert_eq!(leftovers.len(), 1);
+
+        store.remove(&CachePath::new("packages").unwrap()).await.unwrap();
+        assert!(!root.path().join("packages").exists());
+    }
+}
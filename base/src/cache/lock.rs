@@ -0,0 +1,73 @@
+use std::fs::{File, OpenOptions};
+use std::path::PathBuf;
+use anyhow::{Context, Result};
+use fs2::FileExt;
+use super::CacheLock;
+
+/// An advisory lock on a file, shared with every other process. Released when it's dropped.
+struct FileLock {
+    file: File,
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // Closing the file releases the lock anyway, so there's nothing to do if this fails.
+        self.file.unlock().ok();
+    }
+}
+
+/// Locks a file (creating it if needed), waiting for any other process holding the lock to finish first.
+pub async fn lock_file(path: PathBuf) -> Result<CacheLock> {
+    let file = OpenOptions::new().create(true).truncate(false).write(true).open(&path)
+        .context("Could not open the cache lock file.")?;
+
+    if file.try_lock_exclusive().is_err() {
+        eprintln!("Waiting for another Ibis process to finish with the cache...");
+        let file = tokio::task::spawn_blocking(move || file.lock_exclusive().map(|()| file))
+            .await
+            .context("Could not wait for the cache lock.")?
+            .context("Could not lock the cache.")?;
+        return Ok(CacheLock::new(FileLock { file }));
+    }
+    Ok(CacheLock::new(FileLock { file }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn waits_for_the_lock_to_be_released() {
+        let root = TempDir::new().unwrap();
+        let path = root.path().join("lock");
+        let first = lock_file(path.clone()).await.unwrap();
+
+        let other = OpenOptions::new().write(true).open(&path).unwrap();
+        assert!(other.try_lock_exclusive().is_err());
+
+        let acquired = Arc::new(AtomicBool::new(false));
+        let waiting = {
+            let (path, acquired) = (path.clone(), acquired.clone());
+            tokio::spawn(async move {
+                let lock = lock_file(path).await.unwrap();
+                acquired.store(true, Ordering::SeqCst);
+                lock
+            })
+        };
+        for _ in 0..10 {
+            let _ = tokio::task::yield_now().await;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert!(!acquired.load(Ordering::SeqCst));
+
+        drop(first);
+        let second = waiting.await.unwrap();
+        assert!(acquired.load(Ordering::SeqCst));
+        assert!(other.try_lock_exclusive().is_err());
+        drop(second);
+        assert!(other.try_lock_exclusive().is_ok());
+    }
+}
@@ -0,0 +1,304 @@
+//! Inspecting and cleaning up the cache. Everything here goes through a `CacheStore`, so it can only
+//! ever see or remove files inside the cache.
+
+use std::collections::{BTreeMap, BTreeSet};
+use anyhow::{Result, anyhow};
+use crate::constants::{CONFIG_PATH, INDEX_PATH, INSTALLED_PATH, LOCK_PATH, PACKAGES_PATH, REPOS_PATH};
+use crate::install::repo_dir;
+use crate::installed::InstalledDb;
+use crate::validators::{PACKAGE_SCHEMA_VERSIONS, REPO_SCHEMA_VERSIONS};
+use super::{CacheEntry, CachePath, CacheStore};
+
+/// What a file in the cache is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EntryKind {
+    /// A cached schema, or the HTTP metadata stored with it.
+    Schema,
+    /// A cached schema that no version of Ibis's validators uses anymore.
+    UnusedSchema,
+    /// A file of an installed package version.
+    Package,
+    /// A file of a package's cloned git repository.
+    Repository,
+    /// The database of installed packages.
+    Installed,
+    /// A downloaded registry index.
+    Index,
+    /// The user's config and the lock file.
+    Config,
+    /// A temporary file left behind by a write that never finished.
+    Temporary,
+    /// Anything else.
+    Other,
+}
+
+impl EntryKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EntryKind::Schema => "schemas",
+            EntryKind::UnusedSchema => "unused schemas",
+            EntryKind::Package => "packages",
+            EntryKind::Repository => "git repositories",
+            EntryKind::Installed => "installed database",
+            EntryKind::Index => "index",
+            EntryKind::Config => "config",
+            EntryKind::Temporary => "temporary files",
+            EntryKind::Other => "other",
+        }
+    }
+}
+
+/// Gets the directory a version of a package is installed in, ex. `packages/rust+std@1.0.0`. Slashes in
+/// package names are swapped for `+`, which package names can't contain. Fails if the version is empty or
+/// has a path separator in it, since every version has to be a single directory in `packages`.
+pub fn package_dir(name: &str, version: &str) -> Result<CachePath> {
+    if version.is_empty() || version.contains(['/', '\\']) || name.contains('\\') {
+        return Err(anyhow!("`{}@{}` can't be installed: versions can't be empty or contain `/` or `\\`.", name, version));
+    }
+    Ok(CachePath::new(&format!("{}/{}@{}", PACKAGES_PATH, name.replace('/', "+"), version))?)
+}
+
+/// Splits a path into its `/`-separated parts.
+fn parts(path: &CachePath) -> Vec<String> {
+    path.as_path().iter().map(|part| part.to_string_lossy().into_owned()).collect()
+}
+
+/// Works out what a file in the cache is for from its path.
+pub fn classify(path: &CachePath) -> EntryKind {
+    let parts = parts(path);
+    let name = parts.last().map(String::as_str).unwrap_or("");
+
+    if name.starts_with('.') && name.ends_with(".tmp") {
+        return EntryKind::Temporary;
+    }
+    match parts[0].as_str() {
+        PACKAGES_PATH if parts.len() > 2 => return EntryKind::Package,
+        REPOS_PATH if parts.len() > 2 => return EntryKind::Repository,
+        INDEX_PATH if parts.len() > 1 => return EntryKind::Index,
+        _ => {}
+    }
+    if parts.len() != 1 {
+        return EntryKind::Other;
+    }
+    if name == CONFIG_PATH || name == LOCK_PATH {
+        return EntryKind::Config;
+    }
+    if name == INSTALLED_PATH {
+        return EntryKind::Installed;
+    }
+
+    let schema = name.strip_suffix(".meta.json").unwrap_or(name);
+    let known = PACKAGE_SCHEMA_VERSIONS.iter().chain(REPO_SCHEMA_VERSIONS).any(|version| version.path == schema);
+    if known {
+        EntryKind::Schema
+    }
+    else if schema.ends_with(".min.json") {
+        EntryKind::UnusedSchema
+    }
+    else {
+        EntryKind::Other
+    }
+}
+
+/// How much space one kind of file takes up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KindUsage {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// How much space the cache takes up, broken down by what the files are for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheUsage {
+    pub by_kind: BTreeMap<EntryKind, KindUsage>,
+    /// The installed package versions, ex. `packages/rust+std@1.0.0`.
+    pub package_versions: BTreeSet<String>,
+}
+
+impl CacheUsage {
+    pub fn total(&self) -> KindUsage {
+        self.by_kind.values().fold(KindUsage::default(), |total, usage| KindUsage {
+            files: total.files + usage.files,
+            bytes: total.bytes + usage.bytes,
+        })
+    }
+}
+
+/// Gets the directory of the package version a package file belongs to, or of the repository a
+/// repository file belongs to.
+fn package_version_dir(path: &CachePath) -> String {
+    parts(path)[..2].join("/")
+}
+
+/// Adds up how much space everything in the cache takes up.
+pub async fn usage(store: &dyn CacheStore) -> Result<CacheUsage> {
+    let mut usage = CacheUsage::default();
+    for CacheEntry { path, size } in store.list().await? {
+        let kind = classify(&path);
+        if kind == EntryKind::Package {
+            usage.package_versions.insert(package_version_dir(&path));
+        }
+        let kind_usage = usage.by_kind.entry(kind).or_default();
+        kind_usage.files += 1;
+        kind_usage.bytes += size;
+    }
+    Ok(usage)
+}
+
+/// Finds what `prune()` would remove: temporary files, unused schemas, orphaned package versions, and
+/// the repositories of packages with no installed versions. A package version is orphaned if it isn't
+/// recorded in the installed database, which means it was never fully installed or was uninstalled.
+/// Returns the paths to remove, sorted.
+pub async fn prunable(store: &dyn CacheStore) -> Result<Vec<CachePath>> {
+    let entries = store.list().await?;
+
+    let db = InstalledDb::load(store).await?;
+    let installed: BTreeSet<String> = db.packages.iter()
+        .map(|package| Ok(parts(&package.dir()?).join("/")))
+        .chain(db.packages.iter().map(|package| Ok(parts(&repo_dir(&package.name)?).join("/"))))
+        .collect::<Result<_>>()?;
+
+    let mut paths: BTreeSet<String> = BTreeSet::new();
+    for entry in &entries {
+        match classify(&entry.path) {
+            EntryKind::Temporary | EntryKind::UnusedSchema => { paths.insert(parts(&entry.path).join("/")); }
+            EntryKind::Package | EntryKind::Repository => {
+                let dir = package_version_dir(&entry.path);
+                if !installed.contains(&dir) {
+                    paths.insert(dir);
+                }
+            }
+            _ => {}
+        }
+    }
+    paths.iter().map(|path| Ok(CachePath::new(path)?)).collect()
+}
+
+/// Removes temporary files, unused schemas, orphaned package versions, and unused repositories from the
+/// cache, holding the cache lock so nothing is removed while it's being written. Returns what was removed.
+pub async fn prune(store: &dyn CacheStore) -> Result<Vec<CachePath>> {
+    let _lock = store.lock().await?;
+    let paths = prunable(store).await?;
+    for path in &paths {
+        store.remove(path).await?;
+    }
+    Ok(paths)
+}
+
+/// Removes everything in the cache except the user's config, holding the cache lock. This uninstalls
+/// every package. Returns the number of files removed.
+pub async fn clear(store: &dyn CacheStore) -> Result<usize> {
+    let _lock = store.lock().await?;
+    let entries: Vec<CacheEntry> = store.list().await?.into_iter()
+        .filter(|entry| classify(&entry.path) != EntryKind::Config)
+        .collect();
+
+    // Remove whole top-level directories so no empty directories are left behind.
+    let top_level: BTreeSet<String> = entries.iter().map(|entry| parts(&entry.path)[0].clone()).collect();
+    for path in top_level {
+        store.remove(&CachePath::new(&path)?).await?;
+    }
+    Ok(entries.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::MemoryStore;
+    use crate::installed::{InstalledPackage, Revision};
+
+    /// A cache with an installed version of `example`, and something of every other kind next to it.
+    async fn cache() -> MemoryStore {
+        let store = MemoryStore::new();
+        for file in &[
+            "config.json",
+            "schema-v1.min.json",
+            "schema-v1.min.json.meta.json",
+            "repo-schema-v1.min.json",
+            "schema.min.json",
+            "schema.min.json.meta.json",
+            "packages/example@1.0.0/ibis.json",
+            "packages/example@0.9.0/ibis.json",
+            "packages/.example.123.partial/ibis.json",
+            "repos/example.git/HEAD",
+            "repos/gone.git/HEAD",
+            "index/ibis.json",
+            ".installed.json.123-0.tmp",
+            "packages/example@1.0.0/.intro.html.123-1.tmp",
+            "notes.txt",
+        ] {
+            store.write(&CachePath::new(file).unwrap(), b"{}").await.unwrap();
+        }
+        InstalledDb::update(&store, |db| {
+            db.insert(InstalledPackage {
+                name: String::from("example"),
+                version: String::from("1.0.0"),
+                source: String::from("https://example.com/example.git"),
+                revision: Revision::Git { commit: String::from("0123456789abcdef") },
+                installed_at: 0,
+                size: 2,
+                manifest: String::from("packages/example@1.0.0/ibis.json"),
+            });
+            Ok(())
+        }).await.unwrap();
+        store
+    }
+
+    async fn files(store: &MemoryStore) -> Vec<String> {
+        let mut files: Vec<String> = store.list().await.unwrap().iter().map(|entry| entry.path.to_string()).collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn classifies_files() {
+        let kind = |path: &str| classify(&CachePath::new(path).unwrap());
+        assert_eq!(kind("schema-v1.min.json"), EntryKind::Schema);
+        assert_eq!(kind("repo-schema-v1.min.json.meta.json"), EntryKind::Schema);
+        assert_eq!(kind("schema.min.json"), EntryKind::UnusedSchema);
+        assert_eq!(kind("packages/example@1.0.0/ibis.json"), EntryKind::Package);
+        assert_eq!(kind("repos/example.git/HEAD"), EntryKind::Repository);
+        assert_eq!(kind("installed.json"), EntryKind::Installed);
+        assert_eq!(kind("index/ibis.json"), EntryKind::Index);
+        assert_eq!(kind("config.json"), EntryKind::Config);
+        assert_eq!(kind(".lock"), EntryKind::Config);
+        assert_eq!(kind("packages/example@1.0.0/.intro.html.1-0.tmp"), EntryKind::Temporary);
+        assert_eq!(kind("notes.txt"), EntryKind::Other);
+        assert_eq!(kind("packages/stray.json"), EntryKind::Other);
+    }
+
+    #[tokio::test]
+    async fn prunes_what_is_no_longer_used() {
+        let store = cache().await;
+        let removed: Vec<String> = prune(&store).await.unwrap().iter().map(CachePath::to_string).collect();
+        assert_eq!(removed, vec![
+            ".installed.json.123-0.tmp",
+            "packages/.example.123.partial",
+            "packages/example@0.9.0",
+            "packages/example@1.0.0/.intro.html.123-1.tmp",
+            "repos/gone.git",
+            "schema.min.json",
+            "schema.min.json.meta.json",
+        ]);
+        assert_eq!(files(&store).await, vec![
+            "config.json",
+            "index/ibis.json",
+            "installed.json",
+            "notes.txt",
+            "packages/example@1.0.0/ibis.json",
+            "repo-schema-v1.min.json",
+            "repos/example.git/HEAD",
+            "schema-v1.min.json",
+            "schema-v1.min.json.meta.json",
+        ]);
+        assert_eq!(prune(&store).await.unwrap(), vec![]);
+    }
+
+    #[tokio::test]
+    async fn clears_everything_but_the_config() {
+        let store = cache().await;
+        assert_eq!(clear(&store).await.unwrap(), 15);
+        assert_eq!(files(&store).await, vec!["config.json"]);
+        assert!(InstalledDb::load(&store).await.unwrap().packages.is_empty());
+    }
+}
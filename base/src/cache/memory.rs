@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use async_trait::async_trait;
+use anyhow::Result;
+use super::{CacheEntry, CacheLock, CachePath, CacheStore};
+
+/// A store that keeps everything in memory and is dropped with it. Directories only exist implicitly,
+/// as the parents of files.
+#[derive(Default)]
+pub struct MemoryStore {
+    files: Mutex<HashMap<PathBuf, Vec<u8>>>,
+    lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    fn files(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, Vec<u8>>> {
+        // Nothing can panic while the lock is held, so it can't be poisoned in practice.
+        self.files.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl CacheStore for MemoryStore {
+    async fn read(&self, path: &CachePath) -> Result<Option<Vec<u8>>> {
+        Ok(self.files().get(path.as_path()).cloned())
+    }
+
+    async fn write(&self, path: &CachePath, contents: &[u8]) -> Result<()> {
+        self.files().insert(path.as_path().to_path_buf(), contents.to_vec());
+        Ok(())
+    }
+
+    async fn exists(&self, path: &CachePath) -> Result<bool> {
+        Ok(self.files().keys().any(|file| file.starts_with(path.as_path())))
+    }
+
+    async fn remove(&self, path: &CachePath) -> Result<()> {
+        self.files().retain(|file, _| !file.starts_with(path.as_path()));
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<CacheEntry>> {
+        self.files().iter()
+            .map(|(file, contents)| Ok(CacheEntry {
+                path: CachePath::new(&file.to_string_lossy())?,
+                size: contents.len() as u64,
+            }))
+            .collect()
+    }
+
+    async fn lock(&self) -> Result<CacheLock> {
+        Ok(CacheLock::new(self.lock.clone().lock_owned().await))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(path: &str) -> CachePath {
+        CachePath::new(path).unwrap()
+    }
+
+    #[tokio::test]
+    async fn reads_what_was_written() {
+        let store = MemoryStore::new();
+        assert_eq!(store.read(&path("config.json")).await.unwrap(), None);
+        store.write(&path("config.json"), b"{}").await.unwrap();
+        assert_eq!(store.read(&path("config.json")).await.unwrap(), Some(b"{}".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn removes_directories() {
+        let store = MemoryStore::new();
+        store.write(&path("packages/foo/ibis.json"), b"{}").await.unwrap();
+        store.write(&path("packages/foobar/ibis.json"), b"{}").await.unwrap();
+        assert!(store.exists(&path("packages/foo")).await.unwrap());
+
+        store.remove(&path("packages/foo")).await.unwrap();
+        assert!(!store.exists(&path("packages/foo")).await.unwrap());
+        assert!(store.exists(&path("packages/foobar/ibis.json")).await.unwrap());
+    }
+}
@@ -0,0 +1,160 @@
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+/// Errors for paths that could reach outside of the cache directory.
+#[derive(Debug, Error)]
+pub enum CachePathError {
+    #[error("Cache paths can't be empty.")]
+    Empty,
+    #[error("`{0}` is absolute, but cache paths must be relative to the cache directory.")]
+    Absolute(String),
+    #[error("`{0}` contains `..`, which could leave the cache directory.")]
+    ParentDir(String),
+    #[error("`{0}` goes through a symbolic link that leaves the cache directory.")]
+    Escapes(String),
+    #[error("Could not check `{path}`: {source}")]
+    Io { path: String, source: std::io::Error },
+}
+
+/// A path inside the cache directory. Can only be created from relative paths without any `..`
+/// components, and is checked for symbolic links that leave the cache directory when it's resolved, so
+/// it can never point to anything above the cache directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachePath(PathBuf);
+
+impl CachePath {
+    /// Checks a path relative to the cache directory. Fails if the path is empty, absolute, or contains
+    /// `..`. `.` components are dropped.
+    pub fn new(path: &str) -> Result<CachePath, CachePathError> {
+        let mut checked = PathBuf::new();
+        for component in Path::new(path).components() {
+            match component {
+                Component::Normal(part) => checked.push(part),
+                Component::CurDir => {}
+                Component::ParentDir => return Err(CachePathError::ParentDir(String::from(path))),
+                Component::RootDir | Component::Prefix(_) => return Err(CachePathError::Absolute(String::from(path))),
+            }
+        }
+        if checked.as_os_str().is_empty() {
+            return Err(CachePathError::Empty);
+        }
+        Ok(CachePath(checked))
+    }
+
+    /// The path, relative to the cache directory.
+    pub fn as_path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Joins the path onto the cache directory `root`, which must exist. Fails if any part of the path
+    /// that already exists is a symbolic link to somewhere outside of `root` (or to nowhere).
+    pub fn resolve(&self, root: &Path) -> Result<PathBuf, CachePathError> {
+        let io_error = |path: &Path, source| CachePathError::Io { path: path.to_string_lossy().into_owned(), source };
+        let canonical_root = root.canonicalize().map_err(|source| io_error(root, source))?;
+
+        let mut path = root.to_path_buf();
+        for part in self.0.iter() {
+            path.push(part);
+            match fs::symlink_metadata(&path) {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    let inside = path.canonicalize().is_ok_and(|target| target.starts_with(&canonical_root));
+                    if !inside {
+                        return Err(CachePathError::Escapes(self.0.to_string_lossy().into_owned()));
+                    }
+                }
+                Ok(_) => {}
+                // Nothing exists here yet, so nothing below it can be a link.
+                Err(_) => break,
+            }
+        }
+        Ok(root.join(&self.0))
+    }
+}
+
+impl fmt::Display for CachePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.display())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn rejects(path: &str) -> bool {
+        CachePath::new(path).is_err()
+    }
+
+    #[test]
+    fn accepts_relative_paths() {
+        assert_eq!(CachePath::new("schema.min.json").unwrap().as_path(), Path::new("schema.min.json"));
+        assert_eq!(CachePath::new("./packages/foo/ibis.json").unwrap().as_path(), Path::new("packages/foo/ibis.json"));
+    }
+
+    #[test]
+    fn rejects_parent_dirs() {
+        assert!(rejects(".."));
+        assert!(rejects("../../etc/passwd"));
+        assert!(rejects("packages/../../etc/passwd"));
+        assert!(rejects("packages/.."));
+    }
+
+    #[test]
+    fn rejects_absolute_paths() {
+        assert!(rejects("/etc/passwd"));
+        assert!(rejects("/"));
+        #[cfg(windows)]
+        assert!(rejects("C:\\Windows"));
+    }
+
+    #[test]
+    fn rejects_empty_paths() {
+        assert!(rejects(""));
+        assert!(rejects("."));
+        assert!(rejects("./."));
+    }
+
+    #[test]
+    fn resolves_inside_root() {
+        let root = TempDir::new().unwrap();
+        let path = CachePath::new("packages/foo").unwrap().resolve(root.path()).unwrap();
+        assert_eq!(path, root.path().join("packages/foo"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_symlinks_out_of_root() {
+        use std::os::unix::fs::symlink;
+        let outside = TempDir::new().unwrap();
+        let root = TempDir::new().unwrap();
+        symlink(outside.path(), root.path().join("link")).unwrap();
+        fs::create_dir(root.path().join("dir")).unwrap();
+        symlink("../..", root.path().join("dir/up")).unwrap();
+
+        assert!(CachePath::new("link").unwrap().resolve(root.path()).is_err());
+        assert!(CachePath::new("link/passwd").unwrap().resolve(root.path()).is_err());
+        assert!(CachePath::new("dir/up/etc").unwrap().resolve(root.path()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_dangling_symlinks() {
+        use std::os::unix::fs::symlink;
+        let root = TempDir::new().unwrap();
+        symlink("/nonexistent/ibis", root.path().join("dangling")).unwrap();
+        assert!(CachePath::new("dangling/file").unwrap().resolve(root.path()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn allows_symlinks_inside_root() {
+        use std::os::unix::fs::symlink;
+        let root = TempDir::new().unwrap();
+        fs::create_dir(root.path().join("real")).unwrap();
+        symlink(root.path().join("real"), root.path().join("alias")).unwrap();
+        assert!(CachePath::new("alias/file").unwrap().resolve(root.path()).is_ok());
+    }
+}
@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+use async_trait::async_trait;
+use anyhow::{Context, Result, anyhow};
+use super::CachePath;
+
+/// A file in a store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    pub path: CachePath,
+    /// The size of the file in bytes.
+    pub size: u64,
+}
+
+/// Proof that the cache is locked. The lock is released when this is dropped.
+pub struct CacheLock {
+    _guard: Box<dyn Send + Sync>,
+}
+
+impl CacheLock {
+    /// Wraps whatever releases a store's lock when it's dropped.
+    pub fn new(guard: impl Send + Sync + 'static) -> CacheLock {
+        CacheLock { _guard: Box::new(guard) }
+    }
+}
+
+/// Somewhere Ibis can keep its cache, config, and packages. Every path is relative to the root of the
+/// store and already checked by `CachePath`, so a store can never be used to reach anything outside of it.
+///
+/// `FsStore` keeps everything in the cache directory, and `MemoryStore` keeps everything in memory, for
+/// tests and embedded uses that shouldn't touch the file system.
+#[async_trait]
+pub trait CacheStore: Send + Sync {
+    /// Reads a file. Returns None if it doesn't exist.
+    async fn read(&self, path: &CachePath) -> Result<Option<Vec<u8>>>;
+
+    /// Writes a file, replacing it if it exists. Writes must be atomic: anything reading the file at
+    /// the same time sees either the old or the new file, never part of one.
+    async fn write(&self, path: &CachePath, contents: &[u8]) -> Result<()>;
+
+    /// Returns true if a file or directory exists at the path.
+    async fn exists(&self, path: &CachePath) -> Result<bool>;
+
+    /// Removes a file, or a directory and everything in it. Does nothing if the path doesn't exist.
+    async fn remove(&self, path: &CachePath) -> Result<()>;
+
+    /// Lists every file in the store, in no particular order.
+    async fn list(&self) -> Result<Vec<CacheEntry>>;
+
+    /// Takes a lock on the whole store, waiting for anything else holding it to finish first. Anything
+    /// that changes the store should hold it while it does. The lock isn't reentrant.
+    async fn lock(&self) -> Result<CacheLock>;
+
+    /// Gets where a path is on disk, for things like git that can only work with real files and
+    /// directories. Fails by default, for stores that don't keep anything on disk.
+    async fn local_path(&self, path: &CachePath) -> Result<PathBuf> {
+        Err(anyhow!("{} can't be used from disk, since the store isn't on disk.", path))
+    }
+
+    /// Reads a file as UTF-8. Returns None if it doesn't exist.
+    async fn read_string(&self, path: &CachePath) -> Result<Option<String>> {
+        match self.read(path).await? {
+            Some(bytes) => Ok(Some(String::from_utf8(bytes).context(format!("{} is not valid UTF-8.", path))?)),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! User configuration for Ibis, stored in `config.json` in the cache directory. Every field is optional,
+//! so a missing or empty config file is the same as the defaults.
+
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, Context};
+use crate::cache::{CachePath, CacheStore};
+use crate::constants::{CONFIG_PATH, DEFAULT_CACHE_TTL};
+use std::time::Duration;
+use crate::registry::RegistryConfig;
+use crate::validators::LintConfig;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// Configuration for the lint rules run by `PackageSchema`.
+    pub lint: LintConfig,
+    /// Configuration for files downloaded into the cache.
+    pub cache: CacheConfig,
+    /// The registries packages are looked up in. The public registry is used if this is empty.
+    pub registries: Vec<RegistryConfig>,
+}
+
+/// Configuration for files downloaded into the cache, like the schemas.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// How long a downloaded file is used before checking the server for a newer copy, in seconds.
+    pub ttl: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> CacheConfig {
+        CacheConfig { ttl: DEFAULT_CACHE_TTL }
+    }
+}
+
+impl CacheConfig {
+    pub fn ttl(&self) -> Duration {
+        Duration::from_secs(self.ttl)
+    }
+}
+
+impl Config {
+    /// Loads the config from a store. Returns the default config if there is no config file, and fails
+    /// if the file exists but isn't valid.
+    pub async fn load(store: &dyn CacheStore) -> Result<Config> {
+        match store.read(&CachePath::new(CONFIG_PATH)?).await.context("Could not read config.json.")? {
+            Some(buffer) => serde_json::from_slice(&buffer).context("Failed to parse config.json."),
+            None => Ok(Config::default()),
+        }
+    }
+}
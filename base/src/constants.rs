@@ -1,5 +1,39 @@
-pub const SCHEMA_PATH: &str = "schema.min.json";
 pub const REPO_PATH: &str = "repo.min.json";
 pub const CACHE_PATH: &str = ".ibis";
-pub const SCHEMA_REPO_PATH: &str = "https://raw.githubusercontent.com/samwightt/ibis/master/schema.min.json";
-pub const REPO_REPO_PATH: &str = "https://raw.githubusercontent.com/samwightt/ibis/master/repo.min.json";
\ No newline at end of file
+/// The environment variable that overrides where the cache directory is.
+pub const CACHE_DIR_ENV: &str = "IBIS_HOME";
+/// The name of the cache directory when it's placed in an XDG base directory.
+pub const XDG_DIR_NAME: &str = "ibis";
+pub const CONFIG_PATH: &str = "config.json";
+/// The directory installed packages are kept in, one directory per version.
+pub const PACKAGES_PATH: &str = "packages";
+/// The directory packages' git repositories are cloned into, so they only have to be fetched on the
+/// next install.
+pub const REPOS_PATH: &str = "repos";
+/// The database of installed packages.
+pub const INSTALLED_PATH: &str = "installed.json";
+/// The directory downloaded registry indexes are kept in.
+pub const INDEX_PATH: &str = "index";
+/// The file other Ibis processes lock to get exclusive access to the cache.
+pub const LOCK_PATH: &str = ".lock";
+/// The file in a project directory that lists the packages the project uses.
+pub const DEPS_PATH: &str = "ibis.deps";
+/// The file in a project directory that records exactly what was installed for each of its packages.
+pub const PROJECT_LOCK_PATH: &str = "ibis.lock";
+/// How long downloaded files are used before they're revalidated, in seconds (one day).
+pub const DEFAULT_CACHE_TTL: u64 = 60 * 60 * 24;
+/// The name of the public package registry.
+pub const DEFAULT_REGISTRY_NAME: &str = "ibis";
+/// The public package registry, used when no registries are configured.
+pub const DEFAULT_REGISTRY_URL: &str = "https://raw.githubusercontent.com/samwightt/ibis-repo/main";
+/// Version 1 of the ibis.json schema, the one published on master. Every schema version is cached in its
+/// own file, so files that target different versions can be validated side by side.
+pub const SCHEMA_V1_URL: &str = "https://raw.githubusercontent.com/samwightt/ibis/master/schema.min.json";
+pub const SCHEMA_V1_PATH: &str = "schema-v1.min.json";
+/// Version 1 of the repo.min.json schema.
+pub const REPO_SCHEMA_V1_URL: &str = "https://raw.githubusercontent.com/samwightt/ibis/master/repo.min.json";
+pub const REPO_SCHEMA_V1_PATH: &str = "repo-schema-v1.min.json";
+/// The schema.json built by schema/compile.ts, used when no newer copy has been cached.
+pub const BUILTIN_SCHEMA: &str = include_str!("../../schema.json");
+/// The repo.json built by schema/compile.ts, used when no newer copy has been cached.
+pub const BUILTIN_REPO: &str = include_str!("../../repo.json");
@@ -0,0 +1,192 @@
+//! Local copies of registry indexes. `sync()` downloads a registry's index (its repo.min.json), validates
+//! it with `RepoSchema`, and stores it in `index/` in the cache, so the packages in a registry can be
+//! searched and looked at instantly and offline.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use anyhow::{Result, Context, anyhow};
+use crate::cache::{CachePath, CacheStore, unix_time};
+use crate::constants::{INDEX_PATH, REPO_PATH};
+use crate::models::{RegistryIndex, RegistryListing};
+use crate::packages::read_registry_file;
+use crate::registry::RegistryConfig;
+use crate::resolve::{compare_versions, resolve, VersionSpec};
+use crate::validators::{Severity, Validator};
+
+/// A registry's index, as it was when it was synced.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LocalIndex {
+    /// The name of the registry the index is from.
+    pub registry: String,
+    /// The URL or path the registry was synced from.
+    pub url: String,
+    /// When the index was synced, in seconds since the Unix epoch.
+    pub synced_at: u64,
+    /// Every package in the registry. Repository URLs are already resolved with
+    /// `RegistryConfig::resolve_url()`.
+    pub packages: RegistryIndex,
+}
+
+/// Gets where a registry's index is kept, ex. `index/ibis.json`. Every byte of the registry's name other
+/// than lowercase letters, digits, `-` and `_` is percent-encoded (ex. `my.reg` is kept in
+/// `index/my%2Ereg.json`), so different names never share a file, even on case-insensitive file systems.
+pub fn index_path(registry: &str) -> Result<CachePath> {
+    let name: String = registry.bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' => char::from(byte).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect();
+    Ok(CachePath::new(&format!("{}/{}.json", INDEX_PATH, name))?)
+}
+
+impl LocalIndex {
+    /// Loads the synced index of a registry. Returns None if it has never been synced, or if it was
+    /// synced from a different URL than the registry has now.
+    pub async fn load(store: &dyn CacheStore, registry: &RegistryConfig) -> Result<Option<LocalIndex>> {
+        let index: LocalIndex = match store.read(&index_path(&registry.name)?).await? {
+            Some(buffer) => serde_json::from_slice(&buffer).context(format!("Failed to parse the index of {}.", registry.name))?,
+            None => return Ok(None),
+        };
+        Ok(Some(index).filter(|index| index.url == registry.url))
+    }
+
+    /// Gets a package's listing.
+    pub fn get(&self, name: &str) -> Option<&RegistryListing> {
+        self.packages.iter().find(|listing| listing.name == name)
+    }
+}
+
+/// Downloads a registry's index, validates it with `validator` (a `RepoSchema`), and stores it in the
+/// cache, replacing the index synced before. Fails if the registry has no index or it's invalid.
+pub async fn sync(store: &dyn CacheStore, validator: &(impl Validator + Sync), registry: &RegistryConfig) -> Result<LocalIndex> {
+    let body = read_registry_file(registry, REPO_PATH).await?
+        .context(format!("{} has no index ({}).", registry.name, REPO_PATH))?;
+    let val: Value = serde_json::from_str(&body).context(format!("Failed to parse the index of {}.", registry.name))?;
+
+    let report = validator.validate(&val).await.context("Could not validate the index.")?;
+    if !report.is_valid() {
+        let errors: Vec<String> = report.diagnostics.iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .map(|diagnostic| format!("  - {} {}: {}", diagnostic.rule, diagnostic.pointer, diagnostic.message))
+            .collect();
+        return Err(anyhow!("The index of {} is invalid:\n{}", registry.name, errors.join("\n")));
+    }
+
+    let mut packages: RegistryIndex = RegistryIndex::deserialize(&val).context(format!("Failed to parse the index of {}.", registry.name))?;
+    for listing in &mut packages {
+        listing.repo = listing.repo.as_ref().map(|repo| registry.resolve_url(repo));
+        for version in &mut listing.versions {
+            version.url = registry.resolve_url(&version.url);
+        }
+    }
+    let index = LocalIndex { registry: registry.name.clone(), url: registry.url.clone(), synced_at: unix_time(), packages };
+
+    let buffer = serde_json::to_vec(&index).context("Could not serialize the index.")?;
+    let _lock = store.lock().await?;
+    store.write(&index_path(&registry.name)?, &buffer).await.context("Could not save the index.")?;
+    Ok(index)
+}
+
+/// A package found by `search()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit<'a> {
+    /// The name of the registry the package is in.
+    pub registry: &'a str,
+    pub listing: &'a RegistryListing,
+    /// The version `install` would pick, if the package lists any.
+    pub latest: Option<&'a str>,
+}
+
+/// Finds every package whose name contains `query`, ignoring case, in the given indexes. Exact matches
+/// come first, then names that start with the query, then everything else, each sorted by name. Packages
+/// in more than one index are listed once for each.
+pub fn search<'a>(indexes: &'a [LocalIndex], query: &str) -> Vec<SearchHit<'a>> {
+    let query = query.to_lowercase();
+    let mut hits: Vec<(u8, usize, SearchHit)> = Vec::new();
+    for (order, index) in indexes.iter().enumerate() {
+        for listing in &index.packages {
+            let name = listing.name.to_lowercase();
+            let rank = if name == query { 0 } else if name.starts_with(&query) { 1 } else if name.contains(&query) { 2 } else { continue };
+            let latest = resolve(&listing.name, &listing.versions, &VersionSpec::Latest).ok().map(|entry| entry.version.as_str());
+            hits.push((rank, order, SearchHit { registry: &index.registry, listing, latest }));
+        }
+    }
+    hits.sort_by(|(a_rank, a_order, a), (b_rank, b_order, b)| {
+        (a_rank, &a.listing.name, a_order).cmp(&(b_rank, &b.listing.name, b_order))
+    });
+    hits.into_iter().map(|(_, _, hit)| hit).collect()
+}
+
+/// Gets the versions of a package, newest first.
+pub fn sorted_versions(listing: &RegistryListing) -> Vec<&str> {
+    let mut versions: Vec<&str> = listing.versions.iter().map(|entry| entry.version.as_str()).collect();
+    versions.sort_by(|a, b| compare_versions(b, a));
+    versions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+    use crate::cache::MemoryStore;
+    use crate::validators::RepoSchema;
+
+    fn registry(dir: &TempDir, index: &str) -> RegistryConfig {
+        std::fs::write(dir.path().join(REPO_PATH), index).unwrap();
+        RegistryConfig::new("local", &dir.path().to_string_lossy())
+    }
+
+    #[tokio::test]
+    async fn syncs_and_searches() {
+        let (dir, store) = (TempDir::new().unwrap(), Arc::new(MemoryStore::new()));
+        let schema = RepoSchema { offline: true, store: store.clone(), ..RepoSchema::default() };
+        let registry = registry(&dir, r#"[
+            { "name": "rust-std", "versions": [{ "version": "1.70.0", "url": "repos/rust-std" }, { "version": "1.75.0", "url": "repos/rust-std" }] },
+            { "name": "rust", "versions": [] },
+            { "name": "trust", "versions": [] },
+            { "name": "react", "versions": [] }
+        ]"#);
+
+        sync(&*store, &schema, &registry).await.unwrap();
+        let index = LocalIndex::load(&*store, &registry).await.unwrap().unwrap();
+        let indexes = [index];
+
+        let names: Vec<&str> = search(&indexes, "RUST").iter().map(|hit| hit.listing.name.as_str()).collect();
+        assert_eq!(names, vec!["rust", "rust-std", "trust"]);
+        let std = indexes[0].get("rust-std").unwrap();
+        assert_eq!(sorted_versions(std), vec!["1.75.0", "1.70.0"]);
+        assert_eq!(std.versions[0].url, dir.path().join("repos/rust-std").to_string_lossy());
+    }
+
+    #[tokio::test]
+    async fn refuses_invalid_indexes() {
+        let (dir, store) = (TempDir::new().unwrap(), Arc::new(MemoryStore::new()));
+        let schema = RepoSchema { offline: true, store: store.clone(), ..RepoSchema::default() };
+        let registry = registry(&dir, r#"[{ "name": "rust" }]"#);
+
+        assert!(sync(&*store, &schema, &registry).await.is_err());
+        assert_eq!(LocalIndex::load(&*store, &registry).await.unwrap(), None);
+    }
+
+    #[test]
+    fn keeps_registry_names_apart() {
+        let paths: Vec<String> = ["my.reg", "my_reg", "My_reg", "my%2Ereg", "a/b"].iter()
+            .map(|name| index_path(name).unwrap().to_string())
+            .collect();
+        assert_eq!(paths, vec!["index/my%2Ereg.json", "index/my_reg.json", "index/%4Dy_reg.json", "index/my%252%45reg.json", "index/a%2Fb.json"]);
+    }
+
+    #[tokio::test]
+    async fn ignores_indexes_of_moved_registries() {
+        let (dir, store) = (TempDir::new().unwrap(), Arc::new(MemoryStore::new()));
+        let schema = RepoSchema { offline: true, store: store.clone(), ..RepoSchema::default() };
+        let registry = registry(&dir, r#"[{ "name": "rust", "versions": [] }]"#);
+
+        sync(&*store, &schema, &registry).await.unwrap();
+        let moved = RegistryConfig { url: String::from("https://mirror.example.com"), ..registry.clone() };
+        assert_eq!(LocalIndex::load(&*store, &moved).await.unwrap(), None);
+        assert!(LocalIndex::load(&*store, &registry).await.unwrap().is_some());
+    }
+}
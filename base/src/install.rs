@@ -0,0 +1,538 @@
+//! Installing packages. A package's git repository is cloned into `repos/` the first time it's installed
+//! and fetched after that, so installing another version only downloads what changed. The commit to
+//! install is checked out into a staging directory in `packages/`, its ibis.json is validated, and the
+//! staging directory is renamed to the version's directory once everything has passed.
+//!
+//! Which version is installed is picked from the versions the registry lists with `resolve`. Each listed
+//! version has the URL of a git repository, optionally followed by `#<tag, branch, or commit>`. Without
+//! one, the version's tag (`v1.2.3` or `1.2.3`) is installed if the repository has one, and its HEAD
+//! otherwise. Packages with no listed versions are installed from the HEAD of their `repo`.
+//!
+//! The cache lock is held from the clone until the install is recorded in the installed database. If
+//! anything fails along the way, everything the install created is removed again; a crash can only leave
+//! the staging directory behind, which `cache prune` cleans up.
+
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context, anyhow};
+use git2::{FetchOptions, Oid, Repository};
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use crate::cache::{CacheLock, CachePath, CacheStore, unix_time};
+use crate::cache::maintenance::package_dir;
+use crate::constants::{PACKAGES_PATH, REPOS_PATH};
+use crate::installed::{InstalledDb, InstalledPackage, Revision};
+use crate::models::PackageManifest;
+use crate::packages::{find_listing, ListingLookup};
+use crate::registry::RegistryConfig;
+use crate::resolve::{resolve, VersionSpec};
+use crate::validators::{CompiledValidator, PackageSchema, Severity, Validator};
+
+/// The name of the manifest every package has at the root of its repository.
+const MANIFEST_NAME: &str = "ibis.json";
+
+/// What `install()` did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallStatus {
+    /// The version wasn't installed before.
+    Installed,
+    /// The version was installed from a different commit, and has been replaced.
+    Replaced,
+    /// The version was already installed from the same commit, so nothing changed.
+    AlreadyInstalled,
+}
+
+/// The result of installing a package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstallResult {
+    pub package: InstalledPackage,
+    /// The name of the registry the package was found in.
+    pub registry: String,
+    pub status: InstallStatus,
+}
+
+/// Gets the git clone of a package's repository, ex. `repos/rust+std.git`. Slashes in package names are
+/// swapped for `+`, the same as in `package_dir()`.
+pub fn repo_dir(name: &str) -> Result<CachePath> {
+    Ok(CachePath::new(&format!("{}/{}.git", REPOS_PATH, name.replace('/', "+")))?)
+}
+
+/// Gets the directory a package is checked out into before it's validated.
+fn staging_dir(name: &str) -> Result<CachePath> {
+    Ok(CachePath::new(&format!("{}/.{}.{}.partial", PACKAGES_PATH, name.replace('/', "+"), std::process::id()))?)
+}
+
+/// Where to install a package from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Source {
+    /// The URL of the git repository.
+    pub url: String,
+    /// The tag, branch, or commit to install, from the part of the URL after a `#`.
+    pub reference: Option<String>,
+    /// The version the registry lists, if it lists any. Installing fails if the package's ibis.json
+    /// has a different version.
+    pub version: Option<String>,
+}
+
+impl Source {
+    /// A source from a URL, with the tag, branch, or commit to install after a `#` if there is one.
+    pub fn new(url: &str, version: Option<String>) -> Source {
+        let (url, reference) = match url.split_once('#') {
+            Some((url, reference)) => (String::from(url), Some(String::from(reference))),
+            None => (String::from(url), None),
+        };
+        Source { url, reference, version }
+    }
+
+    /// Picks the version to install from a package's listing.
+    pub fn pick(name: &str, lookup: &ListingLookup, spec: &VersionSpec) -> Result<Source> {
+        let listing = &lookup.listing;
+        if listing.versions.is_empty() && *spec == VersionSpec::Latest {
+            let url = listing.repo.clone()
+                .context(format!("The listing of {} in {} has no versions or repo URL.", name, lookup.registry))?;
+            return Ok(Source { url, reference: None, version: None });
+        }
+
+        let entry = resolve(name, &listing.versions, spec)?;
+        Ok(Source::new(&entry.url, Some(entry.version.clone())))
+    }
+
+    /// The URL the package was installed from, as it's recorded in the installed database.
+    pub fn full_url(&self) -> String {
+        match &self.reference {
+            Some(reference) => format!("{}#{}", self.url, reference),
+            None => self.url.clone(),
+        }
+    }
+}
+
+/// Clones a repository into `path` as a bare repository, or fetches every branch and tag into it if it's
+/// already there.
+fn clone_or_fetch(url: &str, path: &Path) -> Result<()> {
+    if !path.exists() {
+        RepoBuilder::new().bare(true).clone(url, path).context(format!("Could not clone {}.", url))?;
+        return Ok(());
+    }
+    let repo = Repository::open_bare(path).context("Could not open the cached clone.")?;
+    repo.remote_set_url("origin", url).context("Could not update the cached clone's URL.")?;
+    let mut remote = repo.find_remote("origin")?;
+    remote.fetch(&["+refs/heads/*:refs/heads/*", "+refs/tags/*:refs/tags/*"], Some(&mut FetchOptions::new()), None)
+        .context(format!("Could not fetch {}.", url))
+}
+
+/// Finds the commit to install in a cloned repository: the source's reference if it has one, then the
+/// tag of its version, then HEAD.
+fn find_commit(path: &Path, source: &Source) -> Result<Oid> {
+    let repo = Repository::open_bare(path).context("Could not open the cached clone.")?;
+    if let Some(reference) = &source.reference {
+        let object = repo.revparse_single(reference).context(format!("{} has no tag, branch, or commit `{}`.", source.url, reference))?;
+        return Ok(object.peel_to_commit().context(format!("`{}` is not a commit.", reference))?.id());
+    }
+    if let Some(version) = &source.version {
+        for tag in &[format!("v{}", version), version.clone()] {
+            if let Ok(object) = repo.revparse_single(&format!("refs/tags/{}", tag)) {
+                return Ok(object.peel_to_commit().context(format!("The tag `{}` is not a commit.", tag))?.id());
+            }
+        }
+    }
+    let commit = repo.head().and_then(|head| head.peel_to_commit()).context(format!("{} has no commits.", source.url))?;
+    Ok(commit.id())
+}
+
+/// Checks out a commit of a bare repository into `target`, which must not exist yet. Filters (ex. line
+/// ending conversion) are disabled, so the files are exactly what was committed.
+fn checkout(repo_path: &Path, commit: Oid, target: &Path) -> Result<()> {
+    let repo = Repository::open_bare(repo_path).context("Could not open the cached clone.")?;
+    let commit = repo.find_commit(commit).context("Could not find the commit to install.")?;
+    std::fs::create_dir_all(target).context("Could not create the staging directory.")?;
+    let mut options = CheckoutBuilder::new();
+    options.target_dir(target).force().disable_filters(true);
+    repo.checkout_tree(commit.as_object(), Some(&mut options)).context("Could not check out the package.")
+}
+
+/// Lists every file in a directory, without following symbolic links.
+fn files_in(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![PathBuf::from(path)];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if std::fs::symlink_metadata(&path)?.is_dir() {
+                dirs.push(path);
+            }
+            else {
+                files.push(path);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Adds up the size of every file in a directory, without following symbolic links.
+fn dir_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    for file in files_in(path)? {
+        size += std::fs::symlink_metadata(&file)?.len();
+    }
+    Ok(size)
+}
+
+/// Hashes the files in a directory with SHA-256. Files are hashed in order of their paths, each as its
+/// `/`-separated path relative to the directory, a NUL, its length, and its contents (or the target of a
+/// symbolic link). Packages are checked out without filters, so an untouched checkout hashes the same on
+/// every platform.
+fn dir_hash(path: &Path) -> Result<String> {
+    let mut files = Vec::new();
+    for file in files_in(path)? {
+        let name = file.strip_prefix(path)?.components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        files.push((name, file));
+    }
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for (name, file) in files {
+        let contents = if std::fs::symlink_metadata(&file)?.file_type().is_symlink() {
+            std::fs::read_link(&file)?.to_string_lossy().into_owned().into_bytes()
+        }
+        else {
+            std::fs::read(&file).context(format!("Could not read {}.", name))?
+        };
+        hasher.update(name.as_bytes());
+        hasher.update([0]);
+        hasher.update((contents.len() as u64).to_le_bytes());
+        hasher.update(&contents);
+    }
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Gets the SHA-256 hash of an installed package's files as they are on disk (see `dir_hash()`), for
+/// checking that the files installed from a lockfile's commit are the ones that were locked, and haven't
+/// been changed since.
+pub async fn content_hash(store: &dyn CacheStore, package: &InstalledPackage) -> Result<String> {
+    let dir = store.local_path(&package.dir()?).await?;
+    blocking(move || dir_hash(&dir)).await
+        .context(format!("Could not hash the files of {}@{}.", package.name, package.version))
+}
+
+/// Runs a blocking git or file system task without blocking the runtime.
+async fn blocking<T: Send + 'static>(task: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(task).await.context("The install task panicked.")?
+}
+
+/// Validates the staged ibis.json, failing with every error found if it's invalid.
+async fn validate(validator: &(impl Validator + Sync), staging: &Path) -> Result<PackageManifest> {
+    let manifest_path = staging.join(MANIFEST_NAME);
+    if !manifest_path.is_file() {
+        return Err(anyhow!("The package has no {} at the root of its repository.", MANIFEST_NAME));
+    }
+    let report = validator.validate_file(&manifest_path.to_string_lossy()).await
+        .context(format!("Could not validate the package's {}.", MANIFEST_NAME))?;
+    if !report.is_valid() {
+        let errors: Vec<String> = report.diagnostics.iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .map(|diagnostic| match diagnostic.suggestions.as_slice() {
+                [] => format!("  - {} {}: {}", diagnostic.rule, diagnostic.pointer, diagnostic.message),
+                suggestions => format!(
+                    "  - {} {}: {} (did you mean {}?)",
+                    diagnostic.rule, diagnostic.pointer, diagnostic.message, suggestions.join(", ")
+                ),
+            })
+            .collect();
+        return Err(anyhow!("The package's {} is invalid:\n{}", MANIFEST_NAME, errors.join("\n")));
+    }
+    let buffer = fs::read_to_string(&manifest_path).await.context(format!("Could not read {}.", MANIFEST_NAME))?;
+    PackageManifest::from_json(&buffer)
+}
+
+/// Moves a validated package from the staging directory into its version's directory and records it,
+/// putting back whatever was installed there before if that fails.
+async fn commit_install(
+    store: &dyn CacheStore,
+    lock: &CacheLock,
+    staging: &Path,
+    package: InstalledPackage,
+) -> Result<InstallStatus> {
+    let target = store.local_path(&package.dir()?).await?;
+    let backup = target.with_file_name(format!(
+        ".{}.old", target.file_name().context("Could not get the package directory name.")?.to_string_lossy()
+    ));
+    let replacing = target.exists();
+    if replacing {
+        fs::rename(&target, &backup).await.context("Could not move the installed version out of the way.")?;
+    }
+
+    let result = async {
+        fs::rename(staging, &target).await.context("Could not move the package into place.")?;
+        InstalledDb::update_locked(store, lock, |db| { db.insert(package); Ok(()) }).await
+    }.await;
+
+    match result {
+        Ok(()) => {
+            if replacing {
+                fs::remove_dir_all(&backup).await.ok();
+            }
+            Ok(if replacing { InstallStatus::Replaced } else { InstallStatus::Installed })
+        }
+        Err(err) => {
+            fs::remove_dir_all(&target).await.ok();
+            if replacing {
+                fs::rename(&backup, &target).await.ok();
+            }
+            Err(err)
+        }
+    }
+}
+
+/// Installs the newest version of a package that matches `spec`, looking it up in `registries` (see
+/// `registry::resolve_registries`). Fails with a `ResolveError` if no listed version matches. See
+/// `install_source()` for the rest.
+pub async fn install(
+    store: &dyn CacheStore,
+    schema: PackageSchema,
+    registries: &[RegistryConfig],
+    name: &str,
+    spec: &VersionSpec,
+) -> Result<InstallResult> {
+    let lookup = find_listing(registries, name).await?;
+    let source = Source::pick(name, &lookup, spec)?;
+    install_source(store, schema, name, &source, &lookup.registry).await
+}
+
+/// Installs a package from a source, ex. an exact commit from a lockfile. `registry` is the name of the
+/// registry the source came from. The package's ibis.json is validated with `schema`, and installing
+/// fails if it's invalid or names a different package or version than the source. Reinstalling a
+/// version from the same commit does nothing, unless its directory is missing. Nothing is left in the
+/// cache if the install fails.
+pub async fn install_source(
+    store: &dyn CacheStore,
+    schema: PackageSchema,
+    name: &str,
+    source: &Source,
+    registry: &str,
+) -> Result<InstallResult> {
+
+    // Get every version of the schema before locking: refreshing a schema takes the lock too.
+    let validator = CompiledValidator::new(schema);
+    for version in validator.versions() {
+        validator.compile(version).await?;
+    }
+
+    let lock = store.lock().await?;
+    let repo_path = store.local_path(&repo_dir(name)?).await?;
+    let staging = store.local_path(&staging_dir(name)?).await?;
+    if let Some(parent) = repo_path.parent() {
+        fs::create_dir_all(parent).await.context("Could not create the repos directory.")?;
+    }
+    if let Some(parent) = staging.parent() {
+        fs::create_dir_all(parent).await.context("Could not create the packages directory.")?;
+    }
+    // A staging directory from this process can only be left over from a crash.
+    fs::remove_dir_all(&staging).await.ok();
+
+    let cloned = !repo_path.exists();
+
+    let result = async {
+        let commit = {
+            let (source, repo_path) = (source.clone(), repo_path.clone());
+            blocking(move || {
+                clone_or_fetch(&source.url, &repo_path)?;
+                find_commit(&repo_path, &source)
+            }).await?
+        };
+        {
+            let (repo_path, staging) = (repo_path.clone(), staging.clone());
+            blocking(move || checkout(&repo_path, commit, &staging)).await?;
+        }
+
+        let manifest = validate(&validator, &staging).await?;
+        if manifest.name != name {
+            return Err(anyhow!("The package's {} is for `{}`, not `{}`.", MANIFEST_NAME, manifest.name, name));
+        }
+        if let Some(version) = source.version.as_ref().filter(|version| **version != manifest.version) {
+            return Err(anyhow!(
+                "The registry lists version {} of {}, but the {} at commit {} is for version {}. Tag the commit as `v{}`, or add `#<commit>` to the version's URL.",
+                version, name, MANIFEST_NAME, commit, manifest.version, version
+            ));
+        }
+
+        let revision = Revision::Git { commit: commit.to_string() };
+        let existing = InstalledDb::load(store).await?.get(&manifest.name, &manifest.version).cloned();
+        if let Some(package) = existing.filter(|package| package.revision == revision) {
+            // The directory can be gone if it was removed by hand; install it again if it is.
+            if store.exists(&package.dir()?).await? {
+                return Ok((package, InstallStatus::AlreadyInstalled));
+            }
+        }
+
+        let size = {
+            let staging = staging.clone();
+            blocking(move || dir_size(&staging)).await?
+        };
+        let dir = package_dir(&manifest.name, &manifest.version)?;
+        let package = InstalledPackage {
+            manifest: format!("{}/{}", dir, MANIFEST_NAME),
+            name: manifest.name,
+            version: manifest.version,
+            source: source.full_url(),
+            revision,
+            installed_at: unix_time(),
+            size,
+        };
+        let status = commit_install(store, &lock, &staging, package.clone()).await?;
+        Ok((package, status))
+    }.await;
+
+    fs::remove_dir_all(&staging).await.ok();
+    if result.is_err() && cloned {
+        fs::remove_dir_all(&repo_path).await.ok();
+    }
+    let (package, status) = result?;
+    Ok(InstallResult { package, registry: String::from(registry), status })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+    use crate::cache::{FsStore, MemoryStore};
+    use crate::config::CacheConfig;
+    use crate::validators::LintConfig;
+
+    /// Commits a package with the given version to a git repository, creating it if needed, and tags it.
+    pub(crate) fn commit_package(repo_path: &Path, version: &str, entry_type: &str) {
+        let repo = Repository::open(repo_path).or_else(|_| Repository::init(repo_path)).unwrap();
+        std::fs::write(repo_path.join("intro.html"), "<p>Intro</p>").unwrap();
+        std::fs::write(repo_path.join(MANIFEST_NAME), format!(
+            r#"{{ "name": "example", "version": "{}", "pages": {{ "intro": {{ "title": "Intro", "path": "intro.html", "entryType": "{}" }} }}, "sidebar": ["intro"] }}"#,
+            version, entry_type,
+        )).unwrap();
+
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("Ibis", "ibis@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        let commit = repo.commit(Some("HEAD"), &signature, &signature, version, &tree, &parents).unwrap();
+        repo.tag_lightweight(&format!("v{}", version), &repo.find_object(commit, None).unwrap(), false).unwrap();
+    }
+
+    /// A directory registry listing the `example` package, kept in `repos/example` next to the listing.
+    pub(crate) fn registry(root: &Path, versions: &[&str]) -> RegistryConfig {
+        let versions: Vec<String> = versions.iter()
+            .map(|version| format!(r#"{{ "version": "{}", "url": "repos/example" }}"#, version))
+            .collect();
+        std::fs::create_dir_all(root.join("packages")).unwrap();
+        std::fs::write(root.join("packages/example.json"), format!(
+            r#"{{ "name": "example", "versions": [{}] }}"#, versions.join(", ")
+        )).unwrap();
+        RegistryConfig::new("local", &root.to_string_lossy())
+    }
+
+    pub(crate) fn schema(store: &FsStore) -> PackageSchema {
+        PackageSchema { offline: true, lint: LintConfig::default(), cache: CacheConfig::default(), store: Arc::new(store.clone()) }
+    }
+
+    #[tokio::test]
+    async fn installs_from_directory_registries() {
+        let (root, cache) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        commit_package(&root.path().join("repos/example"), "1.0.0", "guide");
+        commit_package(&root.path().join("repos/example"), "1.1.0", "guide");
+        let registries = [registry(root.path(), &["1.0.0", "1.1.0"])];
+        let store = FsStore::at(cache.path().to_path_buf());
+
+        let result = install(&store, schema(&store), &registries, "example", &VersionSpec::parse("~1.0")).await.unwrap();
+        assert_eq!((result.package.version.as_str(), result.status), ("1.0.0", InstallStatus::Installed));
+        let result = install(&store, schema(&store), &registries, "example", &VersionSpec::Latest).await.unwrap();
+        assert_eq!(result.package.version, "1.1.0");
+        let result = install(&store, schema(&store), &registries, "example", &VersionSpec::Latest).await.unwrap();
+        assert_eq!(result.status, InstallStatus::AlreadyInstalled);
+
+        let db = InstalledDb::load(&store).await.unwrap();
+        assert_eq!(db.versions("example").count(), 2);
+        assert!(cache.path().join("packages/example@1.0.0/intro.html").is_file());
+    }
+
+    #[tokio::test]
+    async fn reinstalls_missing_directories() {
+        let (root, cache) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        commit_package(&root.path().join("repos/example"), "1.0.0", "guide");
+        let registries = [registry(root.path(), &["1.0.0"])];
+        let store = FsStore::at(cache.path().to_path_buf());
+
+        install(&store, schema(&store), &registries, "example", &VersionSpec::Latest).await.unwrap();
+        std::fs::remove_dir_all(cache.path().join("packages/example@1.0.0")).unwrap();
+        let result = install(&store, schema(&store), &registries, "example", &VersionSpec::Latest).await.unwrap();
+        assert_eq!(result.status, InstallStatus::Installed);
+        assert!(cache.path().join("packages/example@1.0.0/intro.html").is_file());
+        assert_eq!(InstalledDb::load(&store).await.unwrap().versions("example").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn needs_a_store_on_disk() {
+        let root = TempDir::new().unwrap();
+        commit_package(&root.path().join("repos/example"), "1.0.0", "guide");
+        let registries = [registry(root.path(), &["1.0.0"])];
+        let store = Arc::new(MemoryStore::new());
+        let schema = PackageSchema { offline: true, store: store.clone(), ..PackageSchema::default() };
+
+        let err = install(&*store, schema, &registries, "example", &VersionSpec::Latest).await.unwrap_err();
+        assert!(err.to_string().contains("the store isn't on disk"));
+        assert!(InstalledDb::load(&*store).await.unwrap().packages.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rolls_back_failed_installs() {
+        let (root, cache) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        commit_package(&root.path().join("repos/example"), "1.0.0", "not-a-type");
+        let registries = [registry(root.path(), &["1.0.0"])];
+        let store = FsStore::at(cache.path().to_path_buf());
+
+        assert!(install(&store, schema(&store), &registries, "example", &VersionSpec::Latest).await.is_err());
+        let left: Vec<_> = store.list().await.unwrap().into_iter()
+            .filter(|entry| entry.path.to_string() != crate::constants::LOCK_PATH)
+            .collect();
+        assert_eq!(left, vec![]);
+    }
+
+    #[tokio::test]
+    async fn refuses_versions_that_are_paths() {
+        let (root, cache) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        commit_package(&root.path().join("repos/example"), "1.0/evil", "guide");
+        let registries = [registry(root.path(), &["1.0/evil"])];
+        let store = FsStore::at(cache.path().to_path_buf());
+
+        let err = install(&store, schema(&store), &registries, "example", &VersionSpec::Latest).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("versions can't be empty or contain"));
+        assert!(!cache.path().join("packages/example@1.0").exists());
+        assert!(InstalledDb::load(&store).await.unwrap().packages.is_empty());
+    }
+
+    #[tokio::test]
+    async fn hashes_installed_files() {
+        let (root, cache) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        let repo = root.path().join("repos/example");
+        std::fs::create_dir_all(&repo).unwrap();
+        std::fs::write(repo.join(".gitattributes"), "*.txt text eol=crlf\n").unwrap();
+        std::fs::write(repo.join("notes.txt"), "a\nb\n").unwrap();
+        commit_package(&repo, "1.0.0", "guide");
+        let registries = [registry(root.path(), &["1.0.0"])];
+        let store = FsStore::at(cache.path().to_path_buf());
+
+        let result = install(&store, schema(&store), &registries, "example", &VersionSpec::Latest).await.unwrap();
+        let installed = cache.path().join("packages/example@1.0.0/notes.txt");
+        assert_eq!(std::fs::read(&installed).unwrap(), b"a\nb\n");
+
+        let hash = content_hash(&store, &result.package).await.unwrap();
+        std::fs::write(&installed, "a\r\nb\r\n").unwrap();
+        assert_ne!(content_hash(&store, &result.package).await.unwrap(), hash);
+        std::fs::write(&installed, "a\nb\n").unwrap();
+        assert_eq!(content_hash(&store, &result.package).await.unwrap(), hash);
+        std::fs::write(cache.path().join("packages/example@1.0.0/extra.html"), "").unwrap();
+        assert_ne!(content_hash(&store, &result.package).await.unwrap(), hash);
+    }
+}
@@ -0,0 +1,180 @@
+//! The database of installed packages, stored in `installed.json` in the cache directory. It records
+//! every installed version of every package, so commands like list, uninstall, and upgrade know what's
+//! installed without looking through the packages directory.
+//!
+//! The database has a `schema_version` so its format can change later: older versions are migrated
+//! when they're loaded, and versions newer than this build of Ibis understands are refused.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use anyhow::{Result, Context, anyhow};
+use crate::cache::{CacheLock, CachePath, CacheStore};
+use crate::cache::maintenance::package_dir;
+use crate::constants::INSTALLED_PATH;
+
+/// The version of the database format this build of Ibis writes.
+pub const INSTALLED_SCHEMA_VERSION: u64 = 1;
+
+/// The exact revision of a package that was installed.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Revision {
+    /// A package cloned from a git repository, at this commit.
+    Git { commit: String },
+    /// A package downloaded as an archive, with this SHA-256 hash.
+    Archive { sha256: String },
+}
+
+/// A single installed version of a package.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub version: String,
+    /// Where the package was installed from, ex. the URL of its git repository.
+    pub source: String,
+    pub revision: Revision,
+    /// When the package was installed, in seconds since the Unix epoch.
+    pub installed_at: u64,
+    /// The size of the installed files in bytes.
+    pub size: u64,
+    /// The path of the package's ibis.json, relative to the cache root.
+    pub manifest: String,
+}
+
+impl InstalledPackage {
+    /// Gets the directory the package is installed in.
+    pub fn dir(&self) -> Result<CachePath> {
+        package_dir(&self.name, &self.version)
+    }
+}
+
+/// Every installed package. Packages are kept sorted by name, then version.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct InstalledDb {
+    pub schema_version: u64,
+    pub packages: Vec<InstalledPackage>,
+}
+
+impl Default for InstalledDb {
+    fn default() -> InstalledDb {
+        InstalledDb { schema_version: INSTALLED_SCHEMA_VERSION, packages: Vec::new() }
+    }
+}
+
+/// Upgrades a database written by an older version of Ibis to the current format. Fails if it was
+/// written by a newer version of Ibis.
+fn migrate(val: Value) -> Result<InstalledDb> {
+    let version = val.get("schema_version").and_then(Value::as_u64).context("installed.json has no schema_version.")?;
+    match version {
+        INSTALLED_SCHEMA_VERSION => serde_json::from_value(val).context("Failed to parse installed.json."),
+        // Migrations from older versions go here once the format changes.
+        newer if newer > INSTALLED_SCHEMA_VERSION => Err(anyhow!(
+            "installed.json has schema version {}, but this version of Ibis only understands up to {}. Please upgrade Ibis.",
+            newer, INSTALLED_SCHEMA_VERSION
+        )),
+        older => Err(anyhow!("installed.json has unknown schema version {}.", older)),
+    }
+}
+
+impl InstalledDb {
+    /// Loads the database from a store. Returns an empty database if nothing has been installed yet.
+    pub async fn load(store: &dyn CacheStore) -> Result<InstalledDb> {
+        match store.read(&CachePath::new(INSTALLED_PATH)?).await.context("Could not read installed.json.")? {
+            Some(buffer) => migrate(serde_json::from_slice(&buffer).context("Failed to parse installed.json.")?),
+            None => Ok(InstalledDb::default()),
+        }
+    }
+
+    async fn save(&self, store: &dyn CacheStore) -> Result<()> {
+        let buffer = serde_json::to_vec_pretty(self).context("Could not serialize installed.json.")?;
+        store.write(&CachePath::new(INSTALLED_PATH)?, &buffer).await.context("Could not write installed.json.")
+    }
+
+    /// Loads the database, changes it with `change`, and saves it, holding the cache lock the whole time.
+    /// If `change` fails, nothing is saved. Since writes are atomic, other readers see either the old or
+    /// the new database.
+    pub async fn update<T>(store: &dyn CacheStore, change: impl FnOnce(&mut InstalledDb) -> Result<T> + Send) -> Result<T> {
+        let lock = store.lock().await?;
+        InstalledDb::update_locked(store, &lock, change).await
+    }
+
+    /// Same as `update()`, for callers that already hold the cache lock (ex. while installing a package).
+    pub async fn update_locked<T>(store: &dyn CacheStore, _lock: &CacheLock, change: impl FnOnce(&mut InstalledDb) -> Result<T>) -> Result<T> {
+        let mut db = InstalledDb::load(store).await?;
+        let result = change(&mut db)?;
+        db.schema_version = INSTALLED_SCHEMA_VERSION;
+        db.save(store).await?;
+        Ok(result)
+    }
+
+    /// Gets an installed version of a package.
+    pub fn get(&self, name: &str, version: &str) -> Option<&InstalledPackage> {
+        self.packages.iter().find(|package| package.name == name && package.version == version)
+    }
+
+    /// Iterates over every installed version of a package.
+    pub fn versions<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a InstalledPackage> + 'a {
+        self.packages.iter().filter(move |package| package.name == name)
+    }
+
+    /// Records an installed package, replacing the record for the same version if there is one.
+    pub fn insert(&mut self, package: InstalledPackage) {
+        self.remove(&package.name, &package.version);
+        self.packages.push(package);
+        self.packages.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    }
+
+    /// Removes the record of an installed version of a package, returning it.
+    pub fn remove(&mut self, name: &str, version: &str) -> Option<InstalledPackage> {
+        let index = self.packages.iter().position(|package| package.name == name && package.version == version)?;
+        Some(self.packages.remove(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::MemoryStore;
+
+    fn package(name: &str, version: &str) -> InstalledPackage {
+        InstalledPackage {
+            name: String::from(name),
+            version: String::from(version),
+            source: String::from("https://github.com/samwightt/example"),
+            revision: Revision::Git { commit: String::from("0123456789abcdef") },
+            installed_at: 0,
+            size: 0,
+            manifest: format!("packages/{}@{}/ibis.json", name, version),
+        }
+    }
+
+    #[tokio::test]
+    async fn saves_updates() {
+        let store = MemoryStore::new();
+        InstalledDb::update(&store, |db| { db.insert(package("b", "1.0.0")); Ok(()) }).await.unwrap();
+        InstalledDb::update(&store, |db| { db.insert(package("a", "1.0.0")); Ok(()) }).await.unwrap();
+
+        let db = InstalledDb::load(&store).await.unwrap();
+        assert_eq!(db.packages, vec![package("a", "1.0.0"), package("b", "1.0.0")]);
+    }
+
+    #[tokio::test]
+    async fn failed_updates_change_nothing() {
+        let store = MemoryStore::new();
+        InstalledDb::update(&store, |db| { db.insert(package("a", "1.0.0")); Ok(()) }).await.unwrap();
+        let failed: Result<()> = InstalledDb::update(&store, |db| {
+            db.remove("a", "1.0.0");
+            Err(anyhow!("Install failed."))
+        }).await;
+
+        assert!(failed.is_err());
+        assert!(InstalledDb::load(&store).await.unwrap().get("a", "1.0.0").is_some());
+    }
+
+    #[tokio::test]
+    async fn refuses_newer_versions() {
+        let store = MemoryStore::new();
+        store.write(&CachePath::new(INSTALLED_PATH).unwrap(), br#"{ "schema_version": 99, "packages": [] }"#).await.unwrap();
+        assert!(InstalledDb::load(&store).await.is_err());
+    }
+}
@@ -1,4 +1,12 @@
 pub mod cache;
 pub mod validators;
 pub mod constants;
-pub mod packages;
\ No newline at end of file
+pub mod packages;
+pub mod config;
+pub mod installed;
+pub mod registry;
+pub mod models;
+pub mod resolve;
+pub mod install;
+pub mod index;
+pub mod project;
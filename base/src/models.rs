@@ -0,0 +1,83 @@
+//! Typed versions of the JSON files Ibis reads: ibis.json manifests and registry listings. They match
+//! schema/schema.ts and schema/repo.ts, so anything that has passed schema validation can be parsed into
+//! them. Use these instead of digging through a `serde_json::Value`.
+
+mod manifest;
+mod listing;
+
+pub use manifest::{EntryType, PackageManifest, Page};
+pub use listing::{RegistryIndex, RegistryListing, VersionEntry};
+
+use anyhow::{Result, Context};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// Parses a model from JSON. Errors name what was being parsed and what was wrong with it,
+/// ex. `Failed to parse ibis.json: missing field `version` at line 3 column 1`.
+fn parse<T: DeserializeOwned>(json: &str, what: &str) -> Result<T> {
+    serde_json::from_str(json).context(format!("Failed to parse {}", what))
+}
+
+/// Converts an already parsed JSON value into a model, with the same errors as `parse()`.
+fn convert<T: DeserializeOwned>(val: &Value, what: &str) -> Result<T> {
+    T::deserialize(val).context(format!("Failed to parse {}", what))
+}
+
+impl PackageManifest {
+    pub fn from_json(json: &str) -> Result<PackageManifest> {
+        parse(json, "ibis.json")
+    }
+
+    pub fn from_value(val: &Value) -> Result<PackageManifest> {
+        convert(val, "ibis.json")
+    }
+}
+
+impl RegistryListing {
+    /// Parses a listing, naming the package in errors.
+    pub fn from_json(json: &str, package_name: &str) -> Result<RegistryListing> {
+        parse(json, &format!("the registry listing of {}", package_name))
+    }
+}
+
+/// Parses a registry's index (repo.min.json).
+pub fn parse_index(json: &str) -> Result<RegistryIndex> {
+    parse(json, "the registry index")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_manifests() {
+        let manifest = PackageManifest::from_json(r#"{
+            "name": "example",
+            "version": "1.0.0",
+            "pages": {
+                "intro": { "title": "Intro", "path": "intro.html", "entryType": "attibute", "children": ["guide"] },
+                "guide": { "title": "Guide", "path": "guide.html", "entryType": "guide" }
+            },
+            "sidebar": ["intro"]
+        }"#).unwrap();
+
+        assert_eq!(manifest.pages.keys().collect::<Vec<_>>(), vec!["intro", "guide"]);
+        assert_eq!(manifest.pages["intro"].entry_type, EntryType::Attribute);
+        assert!(manifest.pages["guide"].children.is_empty());
+    }
+
+    #[test]
+    fn names_missing_fields() {
+        let err = RegistryListing::from_json(r#"{ "repo": "https://github.com/example/example" }"#, "example").unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(message.contains("the registry listing of example"));
+        assert!(message.contains("missing field `name`"));
+    }
+
+    #[test]
+    fn parses_listings_without_versions() {
+        let listing = RegistryListing::from_json(r#"{ "name": "example", "repo": "https://github.com/example/example" }"#, "example").unwrap();
+        assert_eq!(listing.repo.as_deref(), Some("https://github.com/example/example"));
+        assert!(listing.versions.is_empty());
+    }
+}
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+/// A version of a package in a registry. Matches `VersionInterface` in schema/repo.ts.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct VersionEntry {
+    pub version: String,
+    /// The git repository to install this version from, optionally followed by `#` and a tag, branch,
+    /// or commit.
+    pub url: String,
+}
+
+/// A package in a registry, either in the registry's index or in its `packages/<name>.json` file.
+/// Matches `PackageInterface` in schema/repo.ts.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RegistryListing {
+    pub name: String,
+    /// The git repository the package is developed in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    /// Empty for listings in the original `{ "name", "repo" }` format, which only have a repository.
+    #[serde(default)]
+    pub versions: Vec<VersionEntry>,
+}
+
+/// A registry's index of every package in it (repo.min.json). Matches `RootType` in schema/repo.ts.
+pub type RegistryIndex = Vec<RegistryListing>;
@@ -0,0 +1,217 @@
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The type of entry a page is. Matches `EntryTypes` in schema/schema.ts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryType {
+    Annotation,
+    /// Also accepts the legacy misspelling `attibute`.
+    #[serde(alias = "attibute")]
+    Attribute,
+    Binding,
+    Builtin,
+    Callback,
+    Category,
+    Class,
+    Command,
+    Component,
+    Constant,
+    Constructor,
+    Define,
+    Delegate,
+    Diagram,
+    Directive,
+    Element,
+    Entry,
+    Enum,
+    Environment,
+    Error,
+    Event,
+    Exception,
+    Extension,
+    Field,
+    File,
+    Framework,
+    Function,
+    Global,
+    Guide,
+    Hook,
+    Instance,
+    Instruction,
+    Interface,
+    Keyword,
+    Library,
+    Literal,
+    Macro,
+    Method,
+    Mixin,
+    Modifier,
+    Module,
+    Namespace,
+    Notation,
+    Object,
+    Operator,
+    Option,
+    Package,
+    Parameter,
+    Plugin,
+    Procedure,
+    Property,
+    Protocol,
+    Provider,
+    Provisioner,
+    Query,
+    Record,
+    Resource,
+    Sample,
+    Section,
+    Service,
+    Setting,
+    Shortcut,
+    Statement,
+    Struct,
+    Style,
+    Subroutine,
+    Tag,
+    Test,
+    Trait,
+    Type,
+    Union,
+    Value,
+    Variable,
+    Word,
+}
+
+impl EntryType {
+    /// The name of the entry type in ibis.json, ex. `function`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EntryType::Annotation => "annotation",
+            EntryType::Attribute => "attribute",
+            EntryType::Binding => "binding",
+            EntryType::Builtin => "builtin",
+            EntryType::Callback => "callback",
+            EntryType::Category => "category",
+            EntryType::Class => "class",
+            EntryType::Command => "command",
+            EntryType::Component => "component",
+            EntryType::Constant => "constant",
+            EntryType::Constructor => "constructor",
+            EntryType::Define => "define",
+            EntryType::Delegate => "delegate",
+            EntryType::Diagram => "diagram",
+            EntryType::Directive => "directive",
+            EntryType::Element => "element",
+            EntryType::Entry => "entry",
+            EntryType::Enum => "enum",
+            EntryType::Environment => "environment",
+            EntryType::Error => "error",
+            EntryType::Event => "event",
+            EntryType::Exception => "exception",
+            EntryType::Extension => "extension",
+            EntryType::Field => "field",
+            EntryType::File => "file",
+            EntryType::Framework => "framework",
+            EntryType::Function => "function",
+            EntryType::Global => "global",
+            EntryType::Guide => "guide",
+            EntryType::Hook => "hook",
+            EntryType::Instance => "instance",
+            EntryType::Instruction => "instruction",
+            EntryType::Interface => "interface",
+            EntryType::Keyword => "keyword",
+            EntryType::Library => "library",
+            EntryType::Literal => "literal",
+            EntryType::Macro => "macro",
+            EntryType::Method => "method",
+            EntryType::Mixin => "mixin",
+            EntryType::Modifier => "modifier",
+            EntryType::Module => "module",
+            EntryType::Namespace => "namespace",
+            EntryType::Notation => "notation",
+            EntryType::Object => "object",
+            EntryType::Operator => "operator",
+            EntryType::Option => "option",
+            EntryType::Package => "package",
+            EntryType::Parameter => "parameter",
+            EntryType::Plugin => "plugin",
+            EntryType::Procedure => "procedure",
+            EntryType::Property => "property",
+            EntryType::Protocol => "protocol",
+            EntryType::Provider => "provider",
+            EntryType::Provisioner => "provisioner",
+            EntryType::Query => "query",
+            EntryType::Record => "record",
+            EntryType::Resource => "resource",
+            EntryType::Sample => "sample",
+            EntryType::Section => "section",
+            EntryType::Service => "service",
+            EntryType::Setting => "setting",
+            EntryType::Shortcut => "shortcut",
+            EntryType::Statement => "statement",
+            EntryType::Struct => "struct",
+            EntryType::Style => "style",
+            EntryType::Subroutine => "subroutine",
+            EntryType::Tag => "tag",
+            EntryType::Test => "test",
+            EntryType::Trait => "trait",
+            EntryType::Type => "type",
+            EntryType::Union => "union",
+            EntryType::Value => "value",
+            EntryType::Variable => "variable",
+            EntryType::Word => "word",
+        }
+    }
+}
+
+impl fmt::Display for EntryType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A page in a package. Matches `PageType` in schema/schema.ts.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Page {
+    /// The title of the page.
+    pub title: String,
+    /// A short description of the page. May be used in search results.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// A path to the HTML page, relative to the ibis.json file.
+    pub path: String,
+    pub entry_type: EntryType,
+    /// The IDs of the page's children.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<String>,
+}
+
+/// An ibis.json file. Matches `RootType` in schema/schema.ts.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageManifest {
+    /// A URL to the version of the schema the package targets.
+    #[serde(rename = "$schema", default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    /// The version of the schema the package targets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u64>,
+    pub name: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// The ISO 639-1 language code of the package.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// The page table, keyed by page ID, in the order the pages are listed in the file.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub pages: IndexMap<String, Page>,
+    /// The IDs of the pages in the sidebar.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sidebar: Vec<String>,
+}
@@ -1,28 +1,155 @@
-use git2::Repository;
 use anyhow::{Result, Context, anyhow};
-use serde_json::{Value, Result as SResult};
-
-/// Gets the git repository URL of a given package name. Uses
-/// the `https://github.com/samwightt/ibis-repo` repo by default.
-pub async fn get_package_url(package_name: &String) -> Result<String> {
-    let url = format!("https://raw.githubusercontent.com/samwightt/ibis-repo/main/packages/{}.json", package_name);
-    let body = reqwest::get(&url)
-        .await.context("Failed to get URL.")?
-        .text()
-        .await.context("Failed to get body.")?;
-    
-    let result = parse_listing(&body).context("Could not parse JSON.")?;
-
-    let url = result.get("repo").ok_or(anyhow!("Could not find repo."))?;
-
-    if let Value::String(git_url) = url {
-        Ok(git_url.clone())
+use reqwest::StatusCode;
+use tokio::fs;
+use crate::constants::REPO_PATH;
+use crate::models::{parse_index, RegistryListing};
+use crate::registry::{RegistryConfig, RegistryLocation};
+
+/// The result of looking a package up in the registries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageLookup {
+    /// The git repository URL of the package.
+    pub url: String,
+    /// The name of the registry that had the package.
+    pub registry: String,
+}
+
+/// Reads a file from a registry, ex. `packages/foo.json`. Remote registries are sent the registry's
+/// headers. Returns None if the registry doesn't have the file.
+pub async fn read_registry_file(registry: &RegistryConfig, path: &str) -> Result<Option<String>> {
+    match registry.location()? {
+        RegistryLocation::Remote(_) => {
+            let url = registry.file_url(path);
+            let mut request = reqwest::Client::new().get(&url);
+            for (name, value) in &registry.headers {
+                request = request.header(name.as_str(), value.as_str());
+            }
+
+            let response = request.send().await.context("Failed to get URL.")?;
+            if response.status() == StatusCode::NOT_FOUND {
+                return Ok(None);
+            }
+            if !response.status().is_success() {
+                return Err(anyhow!("{} responded with {}.", url, response.status()));
+            }
+            Ok(Some(response.text().await.context("Failed to get body.")?))
+        }
+        RegistryLocation::Local(dir) => {
+            let file = dir.join(path);
+            if !dir.is_dir() {
+                return Err(anyhow!("{} is not a directory.", dir.display()));
+            }
+            if !file.is_file() {
+                return Ok(None);
+            }
+            Ok(Some(fs::read_to_string(&file).await.context(format!("Could not read {}.", file.display()))?))
+        }
+    }
+}
+
+/// Returns true if a package name can be used as part of a path without leaving the `packages`
+/// directory of a registry.
+fn is_safe_name(package_name: &str) -> bool {
+    !package_name.contains('\\')
+        && package_name.split('/').all(|part| !part.is_empty() && part != "." && part != "..")
+}
+
+/// Gets the listing of a package from a single registry: from `packages/<name>.json`, or from the
+/// registry's index if it's a directory registry that doesn't have that file. Remote indexes are only
+/// downloaded by `index::sync()`, since they list every package in the registry. Repository URLs in the
+/// listing are resolved with `RegistryConfig::resolve_url()`. Returns None if the registry doesn't have
+/// the package.
+pub async fn get_listing(registry: &RegistryConfig, package_name: &str) -> Result<Option<RegistryListing>> {
+    if !is_safe_name(package_name) {
+        return Err(anyhow!("`{}` is not a valid package name.", package_name));
+    }
+
+    let listing = match read_registry_file(registry, &format!("packages/{}.json", package_name)).await? {
+        Some(body) => Some(RegistryListing::from_json(&body, package_name)?),
+        None if matches!(registry.location()?, RegistryLocation::Local(_)) => match read_registry_file(registry, REPO_PATH).await? {
+            Some(body) => parse_index(&body)?.into_iter().find(|listing| listing.name == package_name),
+            None => None,
+        },
+        None => None,
+    };
+
+    Ok(listing.map(|mut listing| {
+        listing.repo = listing.repo.map(|repo| registry.resolve_url(&repo));
+        for version in &mut listing.versions {
+            version.url = registry.resolve_url(&version.url);
+        }
+        listing
+    }))
+}
+
+/// The listing of a package, with the registry it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListingLookup {
+    pub listing: RegistryListing,
+    /// The name of the registry that had the package.
+    pub registry: String,
+}
+
+/// Gets the listing of a package, trying each registry in order until one has the package. Use
+/// `registry::resolve_registries` to get the registries in the right order. Only moves on to the next
+/// registry if a registry doesn't have the package. If looking it up fails any other way (ex. the registry
+/// is down or refuses the request), fails right away, so a lower-priority registry can never answer for a
+/// package that belongs to a registry that's unavailable.
+pub async fn find_listing(registries: &[RegistryConfig], package_name: &str) -> Result<ListingLookup> {
+    for registry in registries {
+        let listing = get_listing(registry, package_name).await
+            .context(format!("Could not look {} up in {}.", package_name, registry.name))?;
+        if let Some(listing) = listing {
+            return Ok(ListingLookup { listing, registry: registry.name.clone() });
+        }
     }
-    else {
-        Err(anyhow!("Could not find repo URL."))
+    let names: Vec<&str> = registries.iter().map(|registry| registry.name.as_str()).collect();
+    Err(anyhow!("Could not find package {} in any registry ({}).", package_name, names.join(", ")))
+}
+
+/// Gets the git repository URL of a given package name from the first registry that has it (see
+/// `find_listing()`).
+pub async fn get_package_url(registries: &[RegistryConfig], package_name: &str) -> Result<PackageLookup> {
+    let ListingLookup { listing, registry } = find_listing(registries, package_name).await?;
+    match listing.repo {
+        Some(url) => Ok(PackageLookup { url, registry }),
+        None => Err(anyhow!("The listing of {} in {} has no repo URL.", package_name, registry)),
     }
 }
 
-fn parse_listing(listing: &String) -> SResult<Value> {
-    serde_json::from_str(&listing)
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn reads_directory_registries() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("packages")).unwrap();
+        std::fs::write(dir.path().join("packages/a.json"), r#"{ "name": "a", "repo": "repos/a", "versions": [] }"#).unwrap();
+        std::fs::write(dir.path().join(REPO_PATH), r#"[{ "name": "b", "versions": [{ "version": "1.0.0", "url": "https://example.com/b" }] }]"#).unwrap();
+        let registry = RegistryConfig::new("local", &dir.path().to_string_lossy());
+
+        let a = get_listing(&registry, "a").await.unwrap().unwrap();
+        assert_eq!(a.repo, Some(dir.path().join("repos/a").to_string_lossy().into_owned()));
+        let b = get_listing(&registry, "b").await.unwrap().unwrap();
+        assert_eq!(b.versions[0].url, "https://example.com/b");
+        assert_eq!(get_listing(&registry, "c").await.unwrap(), None);
+        assert!(get_listing(&registry, "../a").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn stops_at_registries_that_fail() {
+        let (private, public) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        std::fs::create_dir(public.path().join("packages")).unwrap();
+        std::fs::write(public.path().join("packages/a.json"), r#"{ "name": "a", "repo": "repos/a", "versions": [] }"#).unwrap();
+        let public = RegistryConfig::new("public", &public.path().to_string_lossy());
+
+        let empty = RegistryConfig::new("private", &private.path().to_string_lossy());
+        assert_eq!(find_listing(&[empty, public.clone()], "a").await.unwrap().registry, "public");
+
+        let down = RegistryConfig::new("private", &private.path().join("missing").to_string_lossy());
+        let err = find_listing(&[down, public], "a").await.unwrap_err();
+        assert!(format!("{:#}", err).contains("Could not look a up in private"));
+    }
+}
@@ -0,0 +1,334 @@
+//! Projects: directories with an `ibis.deps` file listing the documentation packages the project uses,
+//! so everyone working on it can install the same ones with `ibis sync`.
+//!
+//! `ibis.deps` maps package names to version specs (see `resolve::VersionSpec`):
+//!
+//! ```json
+//! { "dependencies": { "rust": "^1.70", "react": "latest" } }
+//! ```
+//!
+//! Syncing writes `ibis.lock` next to it with the exact version, source, commit, and content hash that
+//! was installed for each package. Later syncs install exactly what's locked as long as the spec in
+//! `ibis.deps` hasn't changed, and a locked sync fails instead of changing the lockfile. Every sync fails
+//! if a package's installed files don't match its content hash.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use anyhow::{Result, Context, anyhow};
+use tokio::fs;
+use crate::cache::{CacheStore, write_atomic};
+use crate::constants::{DEPS_PATH, PROJECT_LOCK_PATH};
+use crate::install::{content_hash, install, install_source, InstallStatus, Source};
+use crate::installed::{InstalledDb, InstalledPackage, Revision};
+use crate::registry::RegistryConfig;
+use crate::resolve::VersionSpec;
+use crate::validators::PackageSchema;
+
+/// The version of the lockfile format this build of Ibis writes.
+pub const LOCKFILE_VERSION: u64 = 1;
+
+/// A project's `ibis.deps` file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Deps {
+    /// The packages the project uses, mapped to the versions of them it can use.
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, String>,
+}
+
+impl Deps {
+    /// Loads the `ibis.deps` file in a project directory.
+    pub async fn load(dir: &Path) -> Result<Deps> {
+        let path = dir.join(DEPS_PATH);
+        let buffer = fs::read_to_string(&path).await.context(format!("Could not read {}.", path.display()))?;
+        serde_json::from_str(&buffer).context(format!("Failed to parse {}.", DEPS_PATH))
+    }
+}
+
+/// A package as it was installed when the lockfile was written.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LockedPackage {
+    pub name: String,
+    /// The version spec from `ibis.deps` this was resolved from. The package is resolved again if the
+    /// spec changes.
+    pub requested: String,
+    pub version: String,
+    /// The name of the registry the package was found in.
+    pub registry: String,
+    /// The git repository the package was installed from.
+    pub source: String,
+    /// The commit that was installed.
+    pub commit: String,
+    /// The SHA-256 hash of the files installed from the commit (see `install::content_hash()`).
+    pub content_hash: String,
+}
+
+/// A project's `ibis.lock` file. Packages are kept sorted by name.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Lockfile {
+    pub lock_version: u64,
+    pub packages: Vec<LockedPackage>,
+}
+
+impl Default for Lockfile {
+    fn default() -> Lockfile {
+        Lockfile { lock_version: LOCKFILE_VERSION, packages: Vec::new() }
+    }
+}
+
+impl Lockfile {
+    /// Loads the `ibis.lock` file in a project directory. Returns None if there isn't one. Fails if it was
+    /// written by a newer version of Ibis.
+    pub async fn load(dir: &Path) -> Result<Option<Lockfile>> {
+        let path = dir.join(PROJECT_LOCK_PATH);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let buffer = fs::read_to_string(&path).await.context(format!("Could not read {}.", path.display()))?;
+        let lockfile: Lockfile = serde_json::from_str(&buffer).context(format!("Failed to parse {}.", PROJECT_LOCK_PATH))?;
+        if lockfile.lock_version > LOCKFILE_VERSION {
+            return Err(anyhow!(
+                "{} has lock version {}, but this version of Ibis only understands up to {}. Please upgrade Ibis.",
+                PROJECT_LOCK_PATH, lockfile.lock_version, LOCKFILE_VERSION
+            ));
+        }
+        Ok(Some(lockfile))
+    }
+
+    /// Writes the lockfile to a project directory, replacing it atomically so it's never left half written.
+    pub async fn save(&self, dir: &Path) -> Result<()> {
+        let mut buffer = serde_json::to_string_pretty(self).context("Could not serialize the lockfile.")?;
+        buffer.push('\n');
+        write_atomic(&dir.join(PROJECT_LOCK_PATH), buffer.as_bytes()).await.context(format!("Could not write {}.", PROJECT_LOCK_PATH))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|package| package.name == name)
+    }
+
+    /// Finds why the lockfile doesn't match `deps`, if it doesn't: a dependency that isn't locked or was
+    /// locked for a different spec, or a locked package that's no longer a dependency.
+    pub fn out_of_date(&self, deps: &Deps) -> Option<String> {
+        for (name, requested) in &deps.dependencies {
+            match self.get(name) {
+                None => return Some(format!("{} is not locked.", name)),
+                Some(locked) if locked.requested != *requested => {
+                    return Some(format!("{} is locked for `{}`, but {} asks for `{}`.", name, locked.requested, DEPS_PATH, requested));
+                }
+                Some(_) => {}
+            }
+        }
+        self.packages.iter()
+            .find(|locked| !deps.dependencies.contains_key(&locked.name))
+            .map(|locked| format!("{} is locked, but is no longer in {}.", locked.name, DEPS_PATH))
+    }
+}
+
+/// What `sync()` did with one dependency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Synced {
+    pub package: LockedPackage,
+    pub status: InstallStatus,
+}
+
+/// The commit a package was installed from.
+fn commit_of(package: &InstalledPackage) -> Result<String> {
+    match &package.revision {
+        Revision::Git { commit } => Ok(commit.clone()),
+        Revision::Archive { .. } => Err(anyhow!("{}@{} was not installed from git.", package.name, package.version)),
+    }
+}
+
+/// Installs a locked package, unless it's already installed from the locked commit. Fails if the
+/// installed files don't match the locked content hash, ex. if they've been changed since.
+async fn install_locked(store: &dyn CacheStore, schema: &impl Fn() -> PackageSchema, locked: &LockedPackage) -> Result<Synced> {
+    let db = InstalledDb::load(store).await?;
+    let installed = match db.get(&locked.name, &locked.version) {
+        Some(package) if package.revision == Revision::Git { commit: locked.commit.clone() } && store.exists(&package.dir()?).await? => {
+            Some(package.clone())
+        }
+        _ => None,
+    };
+
+    let (package, status) = match installed {
+        Some(package) => (package, InstallStatus::AlreadyInstalled),
+        None => {
+            let source = Source {
+                reference: Some(locked.commit.clone()),
+                ..Source::new(&locked.source, Some(locked.version.clone()))
+            };
+            let result = install_source(store, schema(), &locked.name, &source, &locked.registry).await?;
+            (result.package, result.status)
+        }
+    };
+
+    let hash = content_hash(store, &package).await?;
+    if hash != locked.content_hash {
+        return Err(anyhow!(
+            "The files of {}@{} don't match {}: expected content hash {}, found {}.",
+            locked.name, locked.version, PROJECT_LOCK_PATH, locked.content_hash, hash
+        ));
+    }
+    Ok(Synced { package: locked.clone(), status })
+}
+
+/// Installs every dependency of the project in `dir` and writes its lockfile. Dependencies whose spec
+/// hasn't changed since they were locked are installed exactly as locked; the rest are resolved in
+/// `registries` again. If `locked` is true, fails instead if the lockfile is missing or out of date, and
+/// never writes it. `schema` makes the validator for each install.
+pub async fn sync(
+    dir: &Path,
+    store: &dyn CacheStore,
+    schema: impl Fn() -> PackageSchema,
+    registries: &[RegistryConfig],
+    locked: bool,
+) -> Result<Vec<Synced>> {
+    let deps = Deps::load(dir).await?;
+    let lockfile = Lockfile::load(dir).await?;
+    if locked {
+        let lockfile = lockfile.as_ref().context(format!("There is no {}, so it can't be used with --locked.", PROJECT_LOCK_PATH))?;
+        if let Some(reason) = lockfile.out_of_date(&deps) {
+            return Err(anyhow!("{} is out of date: {} Run `ibis sync` without --locked to update it.", PROJECT_LOCK_PATH, reason));
+        }
+    }
+    let lockfile = lockfile.unwrap_or_default();
+
+    let mut synced = Vec::new();
+    for (name, requested) in &deps.dependencies {
+        let result = match lockfile.get(name).filter(|locked| locked.requested == *requested) {
+            Some(locked) => install_locked(store, &schema, locked).await,
+            None => async {
+                let result = install(store, schema(), registries, name, &VersionSpec::parse(requested)).await?;
+                let package = LockedPackage {
+                    name: name.clone(),
+                    requested: requested.clone(),
+                    version: result.package.version.clone(),
+                    registry: result.registry.clone(),
+                    source: result.package.source.clone(),
+                    commit: commit_of(&result.package)?,
+                    content_hash: content_hash(store, &result.package).await?,
+                };
+                Ok(Synced { package, status: result.status })
+            }.await,
+        };
+        synced.push(result.context(format!("Could not sync {}.", name))?);
+    }
+
+    if !locked {
+        let packages = synced.iter().map(|synced| synced.package.clone()).collect();
+        let updated = Lockfile { lock_version: LOCKFILE_VERSION, packages };
+        if Some(&updated) != Lockfile::load(dir).await?.as_ref() {
+            updated.save(dir).await?;
+        }
+    }
+    Ok(synced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use crate::cache::FsStore;
+    use crate::install::tests::{commit_package, registry, schema};
+
+    fn locked(name: &str, requested: &str) -> LockedPackage {
+        LockedPackage {
+            name: String::from(name),
+            requested: String::from(requested),
+            version: String::from("1.0.0"),
+            registry: String::from("ibis"),
+            source: format!("https://github.com/samwightt/{}", name),
+            commit: String::from("0123456789abcdef"),
+            content_hash: String::new(),
+        }
+    }
+
+    fn deps(dependencies: &[(&str, &str)]) -> Deps {
+        Deps { dependencies: dependencies.iter().map(|(name, spec)| (String::from(*name), String::from(*spec))).collect() }
+    }
+
+    #[test]
+    fn finds_out_of_date_lockfiles() {
+        let lockfile = Lockfile { packages: vec![locked("a", "^1"), locked("b", "latest")], ..Lockfile::default() };
+        assert_eq!(lockfile.out_of_date(&deps(&[("a", "^1"), ("b", "latest")])), None);
+        assert!(lockfile.out_of_date(&deps(&[("a", "^2"), ("b", "latest")])).is_some());
+        assert!(lockfile.out_of_date(&deps(&[("a", "^1"), ("b", "latest"), ("c", "latest")])).is_some());
+        assert!(lockfile.out_of_date(&deps(&[("a", "^1")])).is_some());
+    }
+
+    /// A project depending on `example` at `spec`, and a directory registry with its versions.
+    async fn project(spec: &str, versions: &[&str]) -> (TempDir, TempDir, TempDir, [RegistryConfig; 1]) {
+        let (project, root, cache) = (TempDir::new().unwrap(), TempDir::new().unwrap(), TempDir::new().unwrap());
+        for version in versions {
+            commit_package(&root.path().join("repos/example"), version, "guide");
+        }
+        fs::write(project.path().join(DEPS_PATH), format!(r#"{{ "dependencies": {{ "example": "{}" }} }}"#, spec)).await.unwrap();
+        let registries = [registry(root.path(), versions)];
+        (project, root, cache, registries)
+    }
+
+    #[tokio::test]
+    async fn syncs_and_reinstalls_locked_commits() {
+        let (project, root, cache, registries) = project("^1", &["1.0.0"]).await;
+        let store = FsStore::at(cache.path().to_path_buf());
+
+        let err = sync(project.path(), &store, || schema(&store), &registries, true).await.unwrap_err();
+        assert!(err.to_string().contains("There is no ibis.lock"));
+
+        let synced = sync(project.path(), &store, || schema(&store), &registries, false).await.unwrap();
+        assert_eq!((synced[0].package.version.as_str(), synced[0].status), ("1.0.0", InstallStatus::Installed));
+        let lockfile = Lockfile::load(project.path()).await.unwrap().unwrap();
+        assert_eq!(lockfile.packages, vec![synced[0].package.clone()]);
+
+        // A newer version that matches the spec doesn't change what's locked, even once the cache is gone.
+        commit_package(&root.path().join("repos/example"), "1.1.0", "guide");
+        let registries = [registry(root.path(), &["1.0.0", "1.1.0"])];
+        let synced = sync(project.path(), &store, || schema(&store), &registries, true).await.unwrap();
+        assert_eq!(synced[0].status, InstallStatus::AlreadyInstalled);
+        std::fs::remove_dir_all(cache.path()).unwrap();
+        let synced = sync(project.path(), &store, || schema(&store), &registries, true).await.unwrap();
+        assert_eq!(synced[0].status, InstallStatus::Installed);
+        assert_eq!(synced[0].package, lockfile.packages[0]);
+        assert_eq!(Lockfile::load(project.path()).await.unwrap(), Some(lockfile));
+    }
+
+    #[tokio::test]
+    async fn refuses_stale_lockfiles_when_locked() {
+        let (project, _root, cache, registries) = project("^1", &["1.0.0"]).await;
+        let store = FsStore::at(cache.path().to_path_buf());
+        sync(project.path(), &store, || schema(&store), &registries, false).await.unwrap();
+        let lockfile = Lockfile::load(project.path()).await.unwrap();
+
+        fs::write(project.path().join(DEPS_PATH), r#"{ "dependencies": { "example": "~1.0" } }"#).await.unwrap();
+        let err = sync(project.path(), &store, || schema(&store), &registries, true).await.unwrap_err();
+        assert!(err.to_string().contains("ibis.lock is out of date"));
+        assert_eq!(Lockfile::load(project.path()).await.unwrap(), lockfile);
+
+        let synced = sync(project.path(), &store, || schema(&store), &registries, false).await.unwrap();
+        assert_eq!(Lockfile::load(project.path()).await.unwrap().unwrap().packages, vec![synced[0].package.clone()]);
+    }
+
+    #[tokio::test]
+    async fn refuses_commits_with_different_files() {
+        let (project, _root, cache, registries) = project("^1", &["1.0.0"]).await;
+        let store = FsStore::at(cache.path().to_path_buf());
+        sync(project.path(), &store, || schema(&store), &registries, false).await.unwrap();
+
+        let mut lockfile = Lockfile::load(project.path()).await.unwrap().unwrap();
+        lockfile.packages[0].content_hash = "0".repeat(64);
+        lockfile.save(project.path()).await.unwrap();
+        let err = sync(project.path(), &store, || schema(&store), &registries, true).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("don't match ibis.lock"));
+    }
+
+    #[tokio::test]
+    async fn refuses_changed_files() {
+        let (project, _root, cache, registries) = project("^1", &["1.0.0"]).await;
+        let store = FsStore::at(cache.path().to_path_buf());
+        sync(project.path(), &store, || schema(&store), &registries, false).await.unwrap();
+
+        fs::write(cache.path().join("packages/example@1.0.0/intro.html"), "<p>Changed</p>").await.unwrap();
+        let err = sync(project.path(), &store, || schema(&store), &registries, true).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("don't match ibis.lock"));
+    }
+}
@@ -0,0 +1,156 @@
+//! Package registries: the servers packages are looked up in. Any number of registries can be set in
+//! `registries` in the config, each with a priority and the headers (ex. for authentication) to send
+//! with every request to it. Lookups try each registry in order of priority until one has the package.
+//!
+//! A registry can also be a directory on disk, given as a `file://` URL or a plain path, ex. for a mirror
+//! on an air-gapped network. Directory registries are laid out the same as remote ones.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+use url::Url;
+use crate::constants::{DEFAULT_REGISTRY_NAME, DEFAULT_REGISTRY_URL};
+
+/// A registry packages can be looked up in.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RegistryConfig {
+    /// The name of the registry, used to pick it with `--registry` and shown when it answers a lookup.
+    pub name: String,
+    /// The base URL of the registry, or the path of a directory registry. Package listings are at
+    /// `<url>/packages/<name>.json`, or for directory registries, also in the registry's index at
+    /// `<url>/repo.min.json`.
+    pub url: String,
+    /// Registries with a higher priority are tried first. Registries with the same priority are tried
+    /// in the order they're listed in. Defaults to 0.
+    #[serde(default)]
+    pub priority: i64,
+    /// Headers sent with every request to the registry, ex. `{ "Authorization": "Bearer ..." }`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl RegistryConfig {
+    /// A registry with no priority or headers.
+    pub fn new(name: &str, url: &str) -> RegistryConfig {
+        RegistryConfig { name: String::from(name), url: String::from(url), priority: 0, headers: BTreeMap::new() }
+    }
+
+    /// The public Ibis registry, used when no registries are configured.
+    pub fn default_registry() -> RegistryConfig {
+        RegistryConfig::new(DEFAULT_REGISTRY_NAME, DEFAULT_REGISTRY_URL)
+    }
+
+    /// Gets the URL of a file in the registry, ex. `packages/foo.json`.
+    pub fn file_url(&self, path: &str) -> String {
+        format!("{}/{}", self.url.trim_end_matches('/'), path)
+    }
+}
+
+/// Where a registry is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryLocation {
+    /// A registry served over HTTP(S), at this base URL.
+    Remote(String),
+    /// A registry in a directory on disk.
+    Local(PathBuf),
+}
+
+/// Returns true if the URL has a scheme, ex. `https://`. Windows paths like `C:\registry` don't count.
+fn has_scheme(url: &str) -> bool {
+    Url::parse(url).map(|parsed| parsed.scheme().len() > 1).unwrap_or(false)
+}
+
+/// Returns true if the URL is a git scp-style URL, ex. `git@github.com:samwightt/ibis.git`.
+fn is_scp(url: &str) -> bool {
+    url.split_once(':').map(|(host, _)| host.contains('@')).unwrap_or(false)
+}
+
+impl RegistryConfig {
+    /// Works out where the registry is from its URL: `file://` URLs and plain paths are directories,
+    /// and anything else is a remote registry. Relative paths are relative to the current directory.
+    pub fn location(&self) -> Result<RegistryLocation> {
+        if !has_scheme(&self.url) {
+            return Ok(RegistryLocation::Local(PathBuf::from(&self.url)));
+        }
+        let url = Url::parse(&self.url)?;
+        match url.scheme() {
+            "file" => url.to_file_path()
+                .map(RegistryLocation::Local)
+                .map_err(|()| anyhow!("{} is not a valid file URL.", self.url)),
+            "http" | "https" => Ok(RegistryLocation::Remote(self.url.clone())),
+            scheme => Err(anyhow!("Registry {} has an unsupported URL scheme `{}`.", self.name, scheme)),
+        }
+    }
+
+    /// Resolves a repository URL from one of the registry's listings. Relative paths in a directory
+    /// registry's listings are relative to the registry, so a mirror can keep its repositories next to
+    /// its listings. Everything else is returned as is.
+    pub fn resolve_url(&self, url: &str) -> String {
+        match self.location() {
+            Ok(RegistryLocation::Local(dir)) if !has_scheme(url) && !is_scp(url) && !Path::new(url).is_absolute() => {
+                dir.join(url).to_string_lossy().into_owned()
+            }
+            _ => String::from(url),
+        }
+    }
+}
+
+/// Gets the registries to look packages up in, in the order to try them. If `choice` is given, only that
+/// registry is used: either the configured registry with that name, or a registry at that URL.
+/// Uses the public registry if none are configured.
+pub fn resolve_registries(configured: &[RegistryConfig], choice: Option<&str>) -> Vec<RegistryConfig> {
+    let mut registries = if configured.is_empty() { vec![RegistryConfig::default_registry()] } else { configured.to_vec() };
+
+    if let Some(choice) = choice {
+        let chosen = registries.iter()
+            .find(|registry| registry.name == choice)
+            .cloned()
+            .unwrap_or_else(|| RegistryConfig::new(choice, choice));
+        return vec![chosen];
+    }
+
+    // The sort is stable, so registries with the same priority stay in the order they're listed in.
+    registries.sort_by_key(|registry| std::cmp::Reverse(registry.priority));
+    registries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry(name: &str, priority: i64) -> RegistryConfig {
+        RegistryConfig { priority, ..RegistryConfig::new(name, &format!("https://{}.example.com", name)) }
+    }
+
+    fn names(registries: &[RegistryConfig]) -> Vec<&str> {
+        registries.iter().map(|registry| registry.name.as_str()).collect()
+    }
+
+    #[test]
+    fn orders_by_priority_then_config_order() {
+        let configured = [registry("a", 0), registry("b", 10), registry("c", 0)];
+        assert_eq!(names(&resolve_registries(&configured, None)), ["b", "a", "c"]);
+    }
+
+    #[test]
+    fn uses_only_the_chosen_registry() {
+        let configured = [registry("a", 0), registry("b", 10)];
+        assert_eq!(resolve_registries(&configured, Some("a")), [registry("a", 0)]);
+        assert_eq!(resolve_registries(&configured, Some("https://other.example.com"))[0].url, "https://other.example.com");
+    }
+
+    #[test]
+    fn finds_directory_registries() {
+        assert_eq!(RegistryConfig::new("a", "/srv/registry").location().unwrap(), RegistryLocation::Local(PathBuf::from("/srv/registry")));
+        assert_eq!(RegistryConfig::new("a", "file:///srv/registry").location().unwrap(), RegistryLocation::Local(PathBuf::from("/srv/registry")));
+        assert_eq!(RegistryConfig::new("a", "mirror").resolve_url("repos/rust"), "mirror/repos/rust");
+        assert!(matches!(registry("a", 0).location().unwrap(), RegistryLocation::Remote(_)));
+        assert_eq!(registry("a", 0).resolve_url("repos/rust"), "repos/rust");
+    }
+
+    #[test]
+    fn defaults_to_the_public_registry() {
+        assert_eq!(resolve_registries(&[], None), [RegistryConfig::default_registry()]);
+    }
+}
@@ -0,0 +1,181 @@
+//! Picking which version of a package to install from the versions a registry lists for it.
+//!
+//! Versions are compared as semantic versions when they are ones. Registries can also list versions that
+//! aren't semantic versions (ex. `2020-06` or `nightly`). Those are always older than every semantic
+//! version. Among themselves they're compared part by part, with runs of digits compared as numbers,
+//! so `build-9` comes before `build-10`.
+
+mod semver;
+
+pub use semver::{PreRelease, SemverError, Version, VersionReq};
+
+use std::cmp::Ordering;
+use std::fmt;
+use thiserror::Error;
+use crate::models::VersionEntry;
+
+/// Which version of a package to install, from the part of `name@version` after the `@`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionSpec {
+    /// The newest version that isn't a pre-release, or the newest version if they all are. Written as
+    /// `latest`, or by leaving the version off.
+    Latest,
+    /// A semver range, ex. `^1.70` or `>=2, <3`. A full version like `1.2.3` only matches that version.
+    Range(VersionReq),
+    /// A version that isn't a semver range, ex. `nightly`, which must match a listed version exactly.
+    Exact(String),
+}
+
+impl VersionSpec {
+    pub fn parse(spec: &str) -> VersionSpec {
+        let spec = spec.trim();
+        if spec.is_empty() || spec == "latest" {
+            return VersionSpec::Latest;
+        }
+        match VersionReq::parse(spec) {
+            Ok(range) => VersionSpec::Range(range),
+            Err(_) => VersionSpec::Exact(String::from(spec)),
+        }
+    }
+
+    /// Splits `name@version` into the package name and the version spec. Without an `@`, the latest
+    /// version is picked.
+    pub fn split(package: &str) -> (&str, VersionSpec) {
+        match package.split_once('@') {
+            Some((name, spec)) if !name.is_empty() => (name, VersionSpec::parse(spec)),
+            _ => (package, VersionSpec::Latest),
+        }
+    }
+}
+
+/// A version as it's sorted: semantic versions after every version that isn't one.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum SortKey {
+    Other(Vec<Part>),
+    Semver(Version),
+}
+
+/// A run of digits or of anything else in a version that isn't a semantic version.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Part {
+    Number(u64),
+    Text(String),
+}
+
+fn sort_key(version: &str) -> SortKey {
+    if let Ok(version) = Version::parse(version) {
+        return SortKey::Semver(version);
+    }
+    let mut parts = Vec::new();
+    let mut chars = version.chars().peekable();
+    while let Some(&first) = chars.peek() {
+        let digit = first.is_ascii_digit();
+        let mut run = String::new();
+        while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() == digit) {
+            run.push(c);
+            chars.next();
+        }
+        parts.push(match run.parse() {
+            Ok(number) if digit => Part::Number(number),
+            _ => Part::Text(run),
+        });
+    }
+    SortKey::Other(parts)
+}
+
+/// Compares two version strings, semantic or not.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    sort_key(a).cmp(&sort_key(b))
+}
+
+/// Errors that can happen while picking a version.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ResolveError {
+    #[error("{name} has no versions listed.")]
+    NoVersions { name: String },
+    #[error("No version of {name} matches {spec}.")]
+    NoMatch {
+        name: String,
+        spec: String,
+        /// Every version that was considered, newest first.
+        candidates: Vec<String>,
+    },
+}
+
+impl fmt::Display for VersionSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionSpec::Latest => write!(f, "latest"),
+            VersionSpec::Range(range) => write!(f, "{}", range),
+            VersionSpec::Exact(version) => write!(f, "{}", version),
+        }
+    }
+}
+
+/// Picks the newest listed version of a package that matches `spec`.
+pub fn resolve<'a>(name: &str, versions: &'a [VersionEntry], spec: &VersionSpec) -> Result<&'a VersionEntry, ResolveError> {
+    if versions.is_empty() {
+        return Err(ResolveError::NoVersions { name: String::from(name) });
+    }
+    let mut sorted: Vec<(SortKey, &VersionEntry)> = versions.iter().map(|entry| (sort_key(&entry.version), entry)).collect();
+    sorted.sort_by(|a, b| b.0.cmp(&a.0));
+
+    let found = match spec {
+        VersionSpec::Latest => sorted.iter()
+            .find(|(key, _)| matches!(key, SortKey::Semver(version) if !version.is_prerelease()))
+            .or_else(|| sorted.first()),
+        VersionSpec::Range(range) => sorted.iter()
+            .find(|(key, _)| matches!(key, SortKey::Semver(version) if range.matches(version))),
+        VersionSpec::Exact(wanted) => sorted.iter()
+            .find(|(_, entry)| entry.version == *wanted),
+    };
+    found.map(|(_, entry)| *entry).ok_or_else(|| ResolveError::NoMatch {
+        name: String::from(name),
+        spec: spec.to_string(),
+        candidates: sorted.iter().map(|(_, entry)| entry.version.clone()).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(versions: &[&str]) -> Vec<VersionEntry> {
+        versions.iter().map(|version| VersionEntry { version: String::from(*version), url: String::new() }).collect()
+    }
+
+    fn resolved(versions: &[&str], spec: &str) -> Option<String> {
+        resolve("example", &entries(versions), &VersionSpec::parse(spec)).ok().map(|entry| entry.version.clone())
+    }
+
+    #[test]
+    fn picks_the_newest_match() {
+        let versions = ["1.69.0", "1.70.0", "1.75.1", "2.0.0-beta.1", "nightly"];
+        assert_eq!(resolved(&versions, "^1.70").as_deref(), Some("1.75.1"));
+        assert_eq!(resolved(&versions, "latest").as_deref(), Some("1.75.1"));
+        assert_eq!(resolved(&versions, "1.70.0").as_deref(), Some("1.70.0"));
+        assert_eq!(resolved(&versions, "nightly").as_deref(), Some("nightly"));
+        assert_eq!(resolved(&versions, "^2.0.0-beta").as_deref(), Some("2.0.0-beta.1"));
+    }
+
+    #[test]
+    fn orders_other_versions_naturally() {
+        assert_eq!(resolved(&["build-9", "build-10", "build-2"], "latest").as_deref(), Some("build-10"));
+        assert_eq!(compare_versions("2020-06", "0.0.1"), Ordering::Less);
+    }
+
+    #[test]
+    fn lists_candidates_when_nothing_matches() {
+        match resolve("example", &entries(&["1.0.0", "1.2.0"]), &VersionSpec::parse("^2")) {
+            Err(ResolveError::NoMatch { candidates, .. }) => assert_eq!(candidates, vec!["1.2.0", "1.0.0"]),
+            other => panic!("Expected no match, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn splits_specs() {
+        assert_eq!(VersionSpec::split("rust@latest"), ("rust", VersionSpec::Latest));
+        assert_eq!(VersionSpec::split("rust"), ("rust", VersionSpec::Latest));
+        assert_eq!(VersionSpec::split("rust@nightly"), ("rust", VersionSpec::Exact(String::from("nightly"))));
+    }
+}
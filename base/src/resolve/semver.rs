@@ -0,0 +1,390 @@
+//! Semantic versions (https://semver.org) and npm-style version ranges. Only the parts of the syntax
+//! registries need are supported: `^`, `~`, `=`, `>`, `>=`, `<`, `<=`, `x`/`*` wildcards, partial
+//! versions like `1.70`, comparators separated by spaces or commas (all must match), and `||` between
+//! alternatives.
+
+use std::cmp::Ordering;
+use std::fmt;
+use thiserror::Error;
+
+/// Errors that can happen while parsing a version or a range.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SemverError {
+    #[error("`{0}` is not a semantic version.")]
+    InvalidVersion(String),
+    #[error("`{0}` is not a version range.")]
+    InvalidRange(String),
+}
+
+/// A part of a pre-release, ex. `beta` and `2` in `1.0.0-beta.2`. Numeric parts sort before text parts.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PreRelease {
+    Numeric(u64),
+    Text(String),
+}
+
+impl fmt::Display for PreRelease {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreRelease::Numeric(number) => write!(f, "{}", number),
+            PreRelease::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// A semantic version. Build metadata (after a `+`) is ignored, as semver requires.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub pre: Vec<PreRelease>,
+}
+
+impl Version {
+    pub fn new(major: u64, minor: u64, patch: u64) -> Version {
+        Version { major, minor, patch, pre: Vec::new() }
+    }
+
+    /// Parses a version, ex. `1.2.3`, `v1.2.3`, or `1.2.3-beta.1+build`.
+    pub fn parse(version: &str) -> Result<Version, SemverError> {
+        let invalid = || SemverError::InvalidVersion(String::from(version));
+        let partial = Partial::parse(version).ok_or_else(invalid)?;
+        match partial {
+            Partial { major: Some(major), minor: Some(minor), patch: Some(patch), pre } => {
+                Ok(Version { major, minor, patch, pre })
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+
+    fn triple(&self) -> (u64, u64, u64) {
+        (self.major, self.minor, self.patch)
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Version) -> Ordering {
+        self.triple().cmp(&other.triple()).then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+            // A version without a pre-release is newer than any pre-release of it.
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => self.pre.cmp(&other.pre),
+        })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Version) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if !self.pre.is_empty() {
+            let pre: Vec<String> = self.pre.iter().map(PreRelease::to_string).collect();
+            write!(f, "-{}", pre.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+/// A version that may be missing its minor or patch number, or have wildcards for them, ex. `1.70`
+/// or `1.x`. None means the part is missing or a wildcard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Partial {
+    major: Option<u64>,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    pre: Vec<PreRelease>,
+}
+
+impl Partial {
+    fn parse(version: &str) -> Option<Partial> {
+        let version = version.trim();
+        let version = version.strip_prefix('v').unwrap_or(version);
+        let version = version.split('+').next()?;
+        let (numbers, pre) = match version.split_once('-') {
+            Some((numbers, pre)) => (numbers, Some(pre)),
+            None => (version, None),
+        };
+
+        let mut parts = numbers.split('.');
+        let mut next = || -> Option<Option<u64>> {
+            match parts.next() {
+                None | Some("x") | Some("X") | Some("*") => Some(None),
+                Some(part) if !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()) => part.parse().ok().map(Some),
+                Some(_) => None,
+            }
+        };
+        let (major, minor, patch) = (next()?, next()?, next()?);
+        if parts.next().is_some() {
+            return None;
+        }
+        // Anything after a wildcard has to be a wildcard too, ex. `1.x.3` isn't allowed.
+        if (major.is_none() && minor.is_some()) || (minor.is_none() && patch.is_some()) {
+            return None;
+        }
+
+        let pre = match pre {
+            // Only full versions can have a pre-release.
+            Some(pre) if patch.is_some() => {
+                pre.split('.').map(|part| {
+                    if part.is_empty() || !part.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+                        None
+                    }
+                    else if part.chars().all(|c| c.is_ascii_digit()) {
+                        part.parse().ok().map(PreRelease::Numeric)
+                    }
+                    else {
+                        Some(PreRelease::Text(String::from(part)))
+                    }
+                }).collect::<Option<Vec<_>>>()?
+            }
+            Some(_) => return None,
+            None => Vec::new(),
+        };
+        Some(Partial { major, minor, patch, pre })
+    }
+
+    /// The lowest version the partial version covers, ex. `1.2.0` for `1.2`.
+    fn lowest(&self) -> Version {
+        Version {
+            major: self.major.unwrap_or(0),
+            minor: self.minor.unwrap_or(0),
+            patch: self.patch.unwrap_or(0),
+            pre: self.pre.clone(),
+        }
+    }
+
+    /// The lowest version above everything the partial version covers, ex. `1.3.0` for `1.2`. Parts that
+    /// are already as big as they can be carry over, so `1.18446744073709551615` gives `2.0.0`. None if no
+    /// version is above it, ex. for `*`.
+    fn above(&self) -> Option<Version> {
+        let next_major = |major: u64| major.checked_add(1).map(|major| Version::new(major, 0, 0));
+        let next_minor = |major: u64, minor: u64| minor.checked_add(1)
+            .map(|minor| Version::new(major, minor, 0))
+            .or_else(|| next_major(major));
+        match (self.major, self.minor, self.patch) {
+            (None, _, _) => None,
+            (Some(major), None, _) => next_major(major),
+            (Some(major), Some(minor), None) => next_minor(major, minor),
+            (Some(major), Some(minor), Some(patch)) => patch.checked_add(1)
+                .map(|patch| Version::new(major, minor, patch))
+                .or_else(|| next_minor(major, minor)),
+        }
+    }
+
+    /// The same partial version with only its first `parts` parts, ex. `1.2` for `1.2.3` and 2 parts.
+    fn truncate(&self, parts: usize) -> Partial {
+        Partial {
+            major: self.major,
+            minor: self.minor.filter(|_| parts > 1),
+            patch: self.patch.filter(|_| parts > 2),
+            pre: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+/// A single bound on a version, ex. `>=1.2.0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Comparator {
+    op: Op,
+    version: Version,
+}
+
+impl Comparator {
+    fn matches(&self, version: &Version) -> bool {
+        match self.op {
+            Op::Eq => version == &self.version,
+            Op::Gt => version > &self.version,
+            Op::Ge => version >= &self.version,
+            Op::Lt => version < &self.version,
+            Op::Le => version <= &self.version,
+        }
+    }
+}
+
+/// Turns one comparator as it's written, ex. `^1.70`, into the bounds it stands for.
+fn desugar(comparator: &str) -> Option<Vec<Comparator>> {
+    let (op, version) = ["^", "~", ">=", "<=", ">", "<", "="].iter()
+        .find_map(|op| comparator.strip_prefix(op).map(|version| (*op, version)))
+        .unwrap_or(("", comparator));
+    let partial = Partial::parse(version)?;
+    let lowest = partial.lowest();
+    let bound = |op, version| Comparator { op, version };
+    // At least `lowest`, and below `above` if anything is above it.
+    let between = |lowest, above: Option<Version>| {
+        std::iter::once(bound(Op::Ge, lowest)).chain(above.map(|above| bound(Op::Lt, above))).collect()
+    };
+
+    let bounds = match op {
+        "" | "=" if partial.patch.is_some() => vec![bound(Op::Eq, lowest)],
+        "" | "=" => between(lowest, partial.above()),
+        ">" => match partial.above() {
+            _ if partial.patch.is_some() => vec![bound(Op::Gt, lowest)],
+            Some(above) => vec![bound(Op::Ge, above)],
+            // Nothing is above it, so nothing matches.
+            None => vec![bound(Op::Lt, Version::new(0, 0, 0))],
+        },
+        ">=" => vec![bound(Op::Ge, lowest)],
+        "<" => vec![bound(Op::Lt, lowest)],
+        "<=" => match partial.above() {
+            _ if partial.patch.is_some() => vec![bound(Op::Le, lowest)],
+            Some(above) => vec![bound(Op::Lt, above)],
+            None => Vec::new(),
+        },
+        "~" if partial.major.is_none() => Vec::new(),
+        "~" => between(lowest, partial.truncate(2).above()),
+        "^" => {
+            // Everything up to the next change in the first part that isn't zero.
+            let parts = match (partial.major, partial.minor) {
+                (None, _) => return Some(Vec::new()),
+                (Some(major), _) if major > 0 => 1,
+                (Some(_), Some(minor)) if minor > 0 => 2,
+                _ => 3,
+            };
+            between(lowest, partial.truncate(parts).above())
+        }
+        _ => return None,
+    };
+    Some(bounds)
+}
+
+/// A version range, ex. `^1.70`, `>=1.2, <2`, or `1.x || 2.x`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionReq {
+    /// The range as it was written.
+    range: String,
+    /// Each alternative is a list of bounds that must all match.
+    alternatives: Vec<Vec<Comparator>>,
+}
+
+impl VersionReq {
+    pub fn parse(range: &str) -> Result<VersionReq, SemverError> {
+        let invalid = || SemverError::InvalidRange(String::from(range));
+        let alternatives = range.split("||").map(|alternative| {
+            // Allow a space between an operator and its version, ex. `>= 1.2`.
+            let mut comparators = Vec::new();
+            let mut pending_op: Option<&str> = None;
+            for token in alternative.split(|c: char| c.is_whitespace() || c == ',').filter(|token| !token.is_empty()) {
+                if token.chars().all(|c| "^~<>=".contains(c)) {
+                    if pending_op.replace(token).is_some() {
+                        return None;
+                    }
+                    continue;
+                }
+                let comparator = match pending_op.take() {
+                    Some(op) => format!("{}{}", op, token),
+                    None => String::from(token),
+                };
+                comparators.extend(desugar(&comparator)?);
+            }
+            if pending_op.is_some() {
+                return None;
+            }
+            Some(comparators)
+        }).collect::<Option<Vec<_>>>().ok_or_else(invalid)?;
+        Ok(VersionReq { range: String::from(range.trim()), alternatives })
+    }
+
+    /// Returns true if the version is in the range. Pre-releases only match if one of the bounds they're
+    /// checked against is a pre-release of the same version, so `^1.0.0` doesn't match `1.1.0-beta` but
+    /// `^1.1.0-alpha` does.
+    pub fn matches(&self, version: &Version) -> bool {
+        self.alternatives.iter().any(|comparators| {
+            comparators.iter().all(|comparator| comparator.matches(version))
+                && (!version.is_prerelease() || comparators.iter().any(|comparator| {
+                    comparator.version.is_prerelease() && comparator.version.triple() == version.triple()
+                }))
+        })
+    }
+}
+
+impl fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(range: &str, version: &str) -> bool {
+        VersionReq::parse(range).unwrap().matches(&Version::parse(version).unwrap())
+    }
+
+    #[test]
+    fn orders_versions() {
+        let mut versions: Vec<Version> = ["1.0.0", "1.0.0-beta.11", "1.0.0-beta.2", "1.0.0-alpha", "0.9.10", "0.9.9"]
+            .iter().map(|version| Version::parse(version).unwrap()).collect();
+        versions.sort();
+        let sorted: Vec<String> = versions.iter().map(Version::to_string).collect();
+        assert_eq!(sorted, vec!["0.9.9", "0.9.10", "1.0.0-alpha", "1.0.0-beta.2", "1.0.0-beta.11", "1.0.0"]);
+    }
+
+    #[test]
+    fn matches_ranges() {
+        assert!(matches("^1.70", "1.75.2"));
+        assert!(!matches("^1.70", "1.69.0"));
+        assert!(!matches("^1.70", "2.0.0"));
+        assert!(matches("^0.2.3", "0.2.9"));
+        assert!(!matches("^0.2.3", "0.3.0"));
+        assert!(matches("~1.2", "1.2.9"));
+        assert!(!matches("~1.2", "1.3.0"));
+        assert!(matches("1.x", "1.9.0"));
+        assert!(matches("1.2.3", "1.2.3"));
+        assert!(!matches("1.2.3", "1.2.4"));
+        assert!(matches(">= 1.2, < 2", "1.9.9"));
+        assert!(!matches(">1.2", "1.2.5"));
+        assert!(matches("<=1.2", "1.2.5"));
+        assert!(matches("1.x || 3.x", "3.1.0"));
+        assert!(matches("*", "0.0.1"));
+    }
+
+    #[test]
+    fn only_matches_prereleases_when_asked() {
+        assert!(!matches("^1.0.0", "1.1.0-beta"));
+        assert!(matches("^1.1.0-alpha", "1.1.0-beta"));
+        assert!(!matches("^1.1.0-alpha", "1.2.0-beta"));
+    }
+
+    #[test]
+    fn handles_the_largest_versions() {
+        let max = u64::MAX;
+        assert!(matches(&format!("^{}", max), &format!("{}.2.3", max)));
+        assert!(matches(&format!("~1.{}", max), &format!("1.{}.7", max)));
+        assert!(!matches(&format!("~1.{}", max), "2.0.0"));
+        assert!(matches(&format!("<=1.{}", max), &format!("1.{}.9", max)));
+        assert!(!matches(&format!("<=1.{}", max), "2.0.0"));
+        assert!(!matches(&format!(">{}", max), &format!("{}.{}.{}", max, max, max)));
+        assert!(matches(&format!("{}.{}", max, max), &format!("{}.{}.{}", max, max, max)));
+        assert!(matches(&format!("^0.0.{}", max), &format!("0.0.{}", max)));
+        assert!(!matches(&format!("^0.0.{}", max), "0.1.0"));
+    }
+
+    #[test]
+    fn rejects_invalid_ranges() {
+        assert!(VersionReq::parse("nightly").is_err());
+        assert!(VersionReq::parse("1.x.3").is_err());
+        assert!(VersionReq::parse(">=").is_err());
+        assert!(Version::parse("1.2").is_err());
+    }
+}
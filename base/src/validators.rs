@@ -1,58 +1,474 @@
 mod validator;
+mod report;
+mod version;
+mod compiled;
+mod span;
+mod suggest;
+pub mod lint;
+pub mod fix;
 pub use validator::Validator;
+pub use compiled::{CompileError, CompiledSchema, CompiledValidator};
+pub use report::{Diagnostic, Location, Severity, ValidationReport, pointer};
+pub use span::SourceMap;
+pub use version::SchemaVersion;
+pub use lint::LintConfig;
+use crate::cache::{CacheStore, FsStore};
+use crate::config::CacheConfig;
 use crate::constants;
+use crate::models::PackageManifest;
 use async_trait::async_trait;
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use serde_json::Value;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::fs;
 
-pub struct PackageSchema {}
+/// Every version of the ibis.json schema, oldest first.
+pub const PACKAGE_SCHEMA_VERSIONS: &[SchemaVersion] = &[
+  SchemaVersion {
+    version: 1,
+    path: constants::SCHEMA_V1_PATH,
+    url: constants::SCHEMA_V1_URL,
+    builtin: constants::BUILTIN_SCHEMA,
+    deprecated: false,
+  },
+];
+
+/// Every version of the repo.min.json schema, oldest first.
+pub const REPO_SCHEMA_VERSIONS: &[SchemaVersion] = &[
+  SchemaVersion {
+    version: 1,
+    path: constants::REPO_SCHEMA_V1_PATH,
+    url: constants::REPO_SCHEMA_V1_URL,
+    builtin: constants::BUILTIN_REPO,
+    deprecated: false,
+  },
+];
+
+/// Validates ibis.json files. If `offline` is true, only the built-in schema is used. Otherwise the schema
+/// cached in `store` is revalidated once it's older than `cache.ttl`. Files that can be read as a
+/// `PackageManifest` are also checked with the lint rules enabled in `lint`.
+pub struct PackageSchema {
+  pub offline: bool,
+  pub lint: LintConfig,
+  pub cache: CacheConfig,
+  pub store: Arc<dyn CacheStore>,
+}
+
+impl Default for PackageSchema {
+  fn default() -> PackageSchema {
+    PackageSchema {
+      offline: false,
+      lint: LintConfig::default(),
+      cache: CacheConfig::default(),
+      store: Arc::new(FsStore::new()),
+    }
+  }
+}
+
+/// Walks the children graph depth-first and returns every cycle found, each as the list of page IDs
+/// that make it up (with the first ID repeated at the end).
+fn find_cycles<'a>(graph: &BTreeMap<&'a str, Vec<&'a str>>) -> Vec<Vec<&'a str>> {
+  #[derive(Clone, Copy, PartialEq)]
+  enum State { Visiting, Done }
+
+  let mut states: HashMap<&str, State> = HashMap::new();
+  let mut cycles = Vec::new();
+
+  for &root in graph.keys() {
+    if states.contains_key(root) { continue; }
+
+    // Each stack frame is a page ID and the index of the next child to look at.
+    let mut stack: Vec<(&str, usize)> = vec![(root, 0)];
+    states.insert(root, State::Visiting);
+
+    while let Some(&mut (id, ref mut next)) = stack.last_mut() {
+      let children = graph.get(id).map(Vec::as_slice).unwrap_or(&[]);
+      if let Some(&child) = children.get(*next) {
+        *next += 1;
+        match states.get(child) {
+          Some(State::Visiting) => {
+            let start = stack.iter().position(|&(frame, _)| frame == child).unwrap_or(0);
+            let mut cycle: Vec<&str> = stack[start..].iter().map(|&(frame, _)| frame).collect();
+            cycle.push(child);
+            cycles.push(cycle);
+          }
+          Some(State::Done) => {}
+          None => {
+            if graph.contains_key(child) {
+              states.insert(child, State::Visiting);
+              stack.push((child, 0));
+            }
+          }
+        }
+      }
+      else {
+        states.insert(id, State::Done);
+        stack.pop();
+      }
+    }
+  }
+
+  cycles
+}
+
+/// Gets the strings in an array along with their indexes. Returns nothing if the value isn't an array, and
+/// skips anything in it that isn't a string; the schema reports those.
+fn string_items(val: Option<&Value>) -> Vec<(usize, &str)> {
+  val.and_then(Value::as_array)
+    .map(|items| items.iter().enumerate().filter_map(|(i, item)| Some((i, item.as_str()?))).collect())
+    .unwrap_or_default()
+}
+
+/// Checks the parts of a package that JSON Schema can't: every `children` and `sidebar` ID must be a key
+/// in the page table, no page can have more than one parent, and the children graph can't contain cycles.
+/// Works on the raw value so it can run even if the schema found problems elsewhere, and only looks at
+/// the page table, `children`, and `sidebar` where they have the right shape. Adds every problem found
+/// to the report rather than stopping at the first.
+fn check_page_references(val: &Value, report: &mut ValidationReport) {
+  let pages = match val.get("pages").and_then(Value::as_object) {
+    Some(pages) => pages,
+    None => return,
+  };
+
+  let mut graph: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+  let mut parents: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+
+  for (id, page) in pages {
+    let mut children = Vec::new();
+
+    for (i, child) in string_items(page.get("children")) {
+      if !pages.contains_key(child) {
+        report.push(Diagnostic::error(
+          &pointer(&["pages", id, "children", &i.to_string()]),
+          "unknown-child",
+          &format!("Page `{}` lists `{}` as a child, but there is no page with that ID.", id, child),
+        ));
+        continue;
+      }
+      let child_parents = parents.entry(child).or_default();
+      if !child_parents.contains(&id.as_str()) {
+        child_parents.push(id);
+        children.push(child);
+      }
+    }
+
+    graph.insert(id.as_str(), children);
+  }
+
+  for (i, id) in string_items(val.get("sidebar")) {
+    if !pages.contains_key(id) {
+      report.push(Diagnostic::error(
+        &pointer(&["sidebar", &i.to_string()]),
+        "unknown-sidebar-page",
+        &format!("The sidebar lists `{}`, but there is no page with that ID.", id),
+      ));
+    }
+  }
+
+  for (child, child_parents) in &parents {
+    if child_parents.len() > 1 {
+      report.push(Diagnostic::error(
+        &pointer(&["pages", child]),
+        "multiple-parents",
+        &format!("Page `{}` has more than one parent: {}.", child, child_parents.join(", ")),
+      ));
+    }
+  }
+
+  for cycle in find_cycles(&graph) {
+    report.push(Diagnostic::error(
+      &pointer(&["pages", cycle[0], "children"]),
+      "children-cycle",
+      &format!("The children of these pages form a cycle: {}.", cycle.join(" -> ")),
+    ));
+  }
+}
+
+/// Splits a page path into its parts. Both `/` and `\` are treated as path separators.
+fn path_parts(path: &str) -> Vec<&str> {
+  path.split(['/', '\\']).collect()
+}
+
+/// Normalizes a page path so paths that point to the same file compare equal: separators become `/`, and
+/// empty and `.` parts are removed. `./guide\intro.html` becomes `guide/intro.html`.
+fn normalize_path(path: &str) -> String {
+  path_parts(path).into_iter()
+    .filter(|part| !part.is_empty() && *part != ".")
+    .collect::<Vec<_>>()
+    .join("/")
+}
+
+/// Returns true if the path could point outside of the directory it's relative to, either because it's
+/// absolute (including Windows drive paths) or because it has a `..` in it.
+fn escapes_root(path: &str, parts: &[&str]) -> bool {
+  Path::new(path).is_absolute()
+    || path.starts_with('/')
+    || path.starts_with('\\')
+    || parts.first().is_some_and(|first| first.ends_with(':'))
+    || parts.contains(&"..")
+}
+
+/// Checks the `path` of every page against the file system. Each path must be relative to `root` (the
+/// directory the ibis.json is in), stay inside of it, have an HTML extension, and point to a file that exists.
+/// Works on the raw value like `check_page_references()`, skipping pages whose `path` isn't a string.
+async fn check_page_files(val: &Value, root: &Path, report: &mut ValidationReport) {
+  let real_root = fs::canonicalize(root).await.ok();
+  let pages = val.get("pages").and_then(Value::as_object).into_iter().flatten();
+
+  for (id, path) in pages.filter_map(|(id, page)| Some((id, page.get("path")?.as_str()?))) {
+    let path_pointer = pointer(&["pages", id, "path"]);
+    let parts = path_parts(path);
+
+    if escapes_root(path, &parts) {
+      report.push(Diagnostic::error(
+        &path_pointer,
+        "path-outside-package",
+        &format!("Page `{}` has the path `{}`, which points outside of the package.", id, path),
+      ));
+      continue;
+    }
+
+    let extension = parts.last()
+      .and_then(|name| Path::new(name).extension())
+      .map(|ext| ext.to_string_lossy().to_lowercase());
+    if extension.as_deref() != Some("html") && extension.as_deref() != Some("htm") {
+      report.push(Diagnostic::error(
+        &path_pointer,
+        "path-not-html",
+        &format!("Page `{}` has the path `{}`, which is not an HTML file.", id, path),
+      ));
+    }
+
+    let full_path = parts.iter()
+      .filter(|part| !part.is_empty() && **part != ".")
+      .fold(PathBuf::from(root), |full, part| full.join(part));
+
+    match fs::metadata(&full_path).await {
+      Err(_) => report.push(Diagnostic::error(
+        &path_pointer,
+        "missing-page-file",
+        &format!("Page `{}` has the path `{}`, but no file exists there.", id, path),
+      )),
+      Ok(metadata) if metadata.is_dir() => report.push(Diagnostic::error(
+        &path_pointer,
+        "page-path-is-directory",
+        &format!("Page `{}` has the path `{}`, which is a directory instead of a file.", id, path),
+      )),
+      Ok(_) => {
+        // Symlinks inside the package can still point outside of it.
+        if let (Some(real_root), Ok(real_path)) = (&real_root, fs::canonicalize(&full_path).await) {
+          if !real_path.starts_with(real_root) {
+            report.push(Diagnostic::error(
+              &path_pointer,
+              "path-outside-package",
+              &format!("Page `{}` has the path `{}`, which links to a file outside of the package.", id, path),
+            ));
+          }
+        }
+      }
+    }
+  }
+}
 
 #[async_trait]
 impl Validator for PackageSchema {
-  fn path(&self) -> String {
-    String::from(constants::SCHEMA_PATH)
+  fn versions(&self) -> &'static [SchemaVersion] {
+    PACKAGE_SCHEMA_VERSIONS
   }
 
-  fn repo_url(&self) -> String {
-    String::from(constants::SCHEMA_REPO_PATH)
+  fn offline(&self) -> bool {
+    self.offline
   }
-  
-  async fn after_validate(&self, val: &Value) -> Result<()> {
-    // I don't know what I was thinking when I wrote this... This doesn't take into account no children or
-    // the fact that there are multiple pages in a json file???? Seriously don't know what was in my head.
-    // TODO: Fix child validation. There are more pressing issues now though.
-
-    // let pages = val.get("pages").ok_or(anyhow!("Could not get pages."))?;
-    // let mut page_ids: HashSet<String> = HashSet::new();
 
-    // for (_, val) in pages.as_object().ok_or(anyhow!("Could not get keys in pages."))? {
-    //   page_ids.insert(val.to_string());
-    // }
+  fn ttl(&self) -> Duration {
+    self.cache.ttl()
+  }
 
-    // let children = pages.get("children").ok_or(anyhow!("Could not get children."))?.as_array().unwrap();
+  fn store(&self) -> &dyn CacheStore {
+    &*self.store
+  }
 
-    // for child in children {
-    //   if !page_ids.contains(&child.to_string()) { return Err(anyhow!("Error finding child.")) }
-    // }
+  /// Checks the page references, then lints the typed manifest if the file can be read as a
+  /// `PackageManifest`. A file that passes the schema but can't be read as one (ex. if a newer cached
+  /// schema allows something this version of Ibis doesn't understand) gets an `invalid-manifest` error.
+  async fn after_validate(&self, val: &Value, report: &mut ValidationReport) -> Result<()> {
+    let schema_valid = report.is_valid();
+    check_page_references(val, report);
+    match PackageManifest::from_value(val) {
+      Ok(manifest) => lint::lint(&manifest, &self.lint, report),
+      Err(err) if schema_valid => report.push(Diagnostic::error("", "invalid-manifest", &format!("{:#}", err))),
+      Err(_) => {}
+    }
+    Ok(())
+  }
 
+  async fn after_validate_file(&self, val: &Value, dir: &Path, report: &mut ValidationReport) -> Result<()> {
+    check_page_files(val, dir, report).await;
     Ok(())
   }
 }
 
-pub struct RepoSchema {}
+/// Validates the repo.min.json index of a package repository. If `offline` is true, only the built-in
+/// schema is used. Otherwise the schema cached in `store` is revalidated once it's older than `cache.ttl`.
+pub struct RepoSchema {
+  pub offline: bool,
+  pub cache: CacheConfig,
+  pub store: Arc<dyn CacheStore>,
+}
+
+impl Default for RepoSchema {
+  fn default() -> RepoSchema {
+    RepoSchema { offline: false, cache: CacheConfig::default(), store: Arc::new(FsStore::new()) }
+  }
+}
 
 #[async_trait]
 impl Validator for RepoSchema {
-  fn path(&self) -> String {
-    String::from(constants::REPO_PATH)
+  fn versions(&self) -> &'static [SchemaVersion] {
+    REPO_SCHEMA_VERSIONS
   }
 
-  fn repo_url(&self) -> String {
-    String::from(constants::REPO_REPO_PATH)
+  fn offline(&self) -> bool {
+    self.offline
   }
 
-  async fn after_validate(&self, val: &Value) -> Result<()> {
+  fn ttl(&self) -> Duration {
+    self.cache.ttl()
+  }
+
+  fn store(&self) -> &dyn CacheStore {
+    &*self.store
+  }
+
+  async fn after_validate(&self, _val: &Value, _report: &mut ValidationReport) -> Result<()> {
     Ok(())
   }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Runs the page reference checks on a package with the given pages and sidebar, and returns the
+  /// rule and pointer of every problem found.
+  fn check(pages: &str, sidebar: &str) -> Vec<(String, String)> {
+    let val: Value = serde_json::from_str(&format!(
+      r#"{{ "name": "example", "version": "1.0.0", "pages": {}, "sidebar": {} }}"#, pages, sidebar
+    )).unwrap();
+    let mut report = ValidationReport::default();
+    check_page_references(&val, &mut report);
+    report.diagnostics.into_iter().map(|diagnostic| (diagnostic.rule, diagnostic.pointer)).collect()
+  }
+
+  fn page(children: &[&str]) -> String {
+    format!(r#"{{ "title": "Page", "path": "page.html", "entryType": "guide", "children": {:?} }}"#, children)
+  }
+
+  #[test]
+  fn accepts_trees() {
+    let pages = format!(r#"{{ "a": {}, "b": {}, "c": {} }}"#, page(&["b", "c"]), page(&[]), page(&[]));
+    assert_eq!(check(&pages, r#"["a"]"#), vec![]);
+  }
+
+  #[test]
+  fn finds_unknown_ids() {
+    let pages = format!(r#"{{ "a": {} }}"#, page(&["missing"]));
+    assert_eq!(check(&pages, r#"["a", "gone"]"#), vec![
+      (String::from("unknown-child"), String::from("/pages/a/children/0")),
+      (String::from("unknown-sidebar-page"), String::from("/sidebar/1")),
+    ]);
+  }
+
+  #[test]
+  fn finds_pages_with_multiple_parents() {
+    let pages = format!(r#"{{ "a": {}, "b": {}, "c": {} }}"#, page(&["c"]), page(&["c"]), page(&[]));
+    assert_eq!(check(&pages, "[]"), vec![(String::from("multiple-parents"), String::from("/pages/c"))]);
+  }
+
+  #[test]
+  fn finds_cycles() {
+    let pages = format!(r#"{{ "a": {}, "b": {}, "c": {}, "d": {} }}"#, page(&["b"]), page(&["c"]), page(&["a"]), page(&["d"]));
+    let found = check(&pages, "[]");
+    assert!(found.contains(&(String::from("children-cycle"), String::from("/pages/a/children"))));
+    assert!(found.contains(&(String::from("children-cycle"), String::from("/pages/d/children"))));
+    assert_eq!(found.len(), 2);
+  }
+
+  #[test]
+  fn skips_values_with_the_wrong_shape() {
+    let pages = r#"{ "a": { "children": "b" }, "b": { "children": [1, "gone"] } }"#;
+    assert_eq!(check(pages, r#"{ "a": 1 }"#), vec![(String::from("unknown-child"), String::from("/pages/b/children/1"))]);
+    assert_eq!(check("[]", r#"["a"]"#), vec![]);
+  }
+
+  #[tokio::test]
+  async fn reports_references_alongside_schema_errors() {
+    let schema = PackageSchema { offline: true, store: Arc::new(crate::cache::MemoryStore::new()), ..PackageSchema::default() };
+    let mut bad = serde_json::from_str::<Value>(&page(&["a", "gone"])).unwrap();
+    bad["entryType"] = Value::from("not-a-type");
+    let val = serde_json::json!({ "name": "example", "version": "1.0.0", "pages": { "a": bad }, "sidebar": ["a", "missing"] });
+
+    let report = schema.validate(&val).await.unwrap();
+    let found: Vec<(&str, &str)> = report.diagnostics.iter().map(|diagnostic| (diagnostic.rule.as_str(), diagnostic.pointer.as_str())).collect();
+    assert!(found.contains(&("enum", "/pages/a/entryType")), "{:?}", found);
+    assert!(found.contains(&("unknown-child", "/pages/a/children/1")));
+    assert!(found.contains(&("unknown-sidebar-page", "/sidebar/1")));
+    assert!(found.contains(&("children-cycle", "/pages/a/children")));
+    assert!(!found.iter().any(|(rule, _)| *rule == "invalid-manifest"));
+  }
+
+  /// Runs the page file checks on pages with the given paths in `dir`, and returns the rule and pointer
+  /// of every problem found.
+  async fn check_files(dir: &Path, paths: &[&str]) -> Vec<(String, String)> {
+    let pages: serde_json::Map<String, Value> = paths.iter().enumerate()
+      .map(|(i, path)| (format!("p{}", i), serde_json::json!({ "path": path })))
+      .collect();
+    let mut report = ValidationReport::default();
+    check_page_files(&serde_json::json!({ "pages": pages }), dir, &mut report).await;
+    report.diagnostics.into_iter().map(|diagnostic| (diagnostic.rule, diagnostic.pointer)).collect()
+  }
+
+  #[tokio::test]
+  async fn checks_page_files() {
+    let (outside, root) = (tempfile::TempDir::new().unwrap(), tempfile::TempDir::new().unwrap());
+    let dir = root.path().join("package");
+    std::fs::create_dir_all(dir.join("guide.html")).unwrap();
+    std::fs::write(dir.join("intro.html"), "<p>Intro</p>").unwrap();
+    std::fs::write(dir.join("notes.txt"), "Notes").unwrap();
+    std::fs::write(outside.path().join("secret.html"), "<p>Secret</p>").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(outside.path().join("secret.html"), dir.join("link.html")).unwrap();
+
+    assert_eq!(check_files(&dir, &["intro.html", "./intro.htm"]).await, vec![
+      (String::from("missing-page-file"), String::from("/pages/p1/path")),
+    ]);
+    assert_eq!(check_files(&dir, &["missing.html", "guide.html", "notes.txt"]).await, vec![
+      (String::from("missing-page-file"), String::from("/pages/p0/path")),
+      (String::from("page-path-is-directory"), String::from("/pages/p1/path")),
+      (String::from("path-not-html"), String::from("/pages/p2/path")),
+    ]);
+    let absolute = dir.join("intro.html").to_string_lossy().into_owned();
+    assert_eq!(check_files(&dir, &["../package/intro.html", &absolute, "C:\\intro.html"]).await, vec![
+      (String::from("path-outside-package"), String::from("/pages/p0/path")),
+      (String::from("path-outside-package"), String::from("/pages/p1/path")),
+      (String::from("path-outside-package"), String::from("/pages/p2/path")),
+    ]);
+    #[cfg(unix)]
+    assert_eq!(check_files(&dir, &["link.html"]).await, vec![
+      (String::from("path-outside-package"), String::from("/pages/p0/path")),
+    ]);
+  }
+
+  #[tokio::test]
+  async fn checks_page_files_alongside_schema_errors() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let val = serde_json::json!({ "pages": { "a": { "path": "a.html", "entryType": "not-a-type" }, "b": { "path": 1 } } });
+    let mut report = ValidationReport::default();
+    check_page_files(&val, dir.path(), &mut report).await;
+    let found: Vec<&str> = report.diagnostics.iter().map(|diagnostic| diagnostic.pointer.as_str()).collect();
+    assert_eq!(found, vec!["/pages/a/path"]);
+  }
+}
@@ -0,0 +1,212 @@
+use serde_json::Value;
+use valico::json_schema::{Scope, SchemaError};
+use valico::common::error::ValicoError;
+use async_trait::async_trait;
+use anyhow::Result;
+use thiserror::Error;
+use tokio::sync::Mutex;
+use url::Url;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::cache::CacheStore;
+use super::{Diagnostic, SchemaVersion, ValidationReport, Validator};
+use super::suggest::suggest_enum;
+
+/// Errors that can happen while compiling a schema.
+#[derive(Debug, Error)]
+pub enum CompileError {
+    #[error("Schema version {version} is not a valid JSON schema: {source}")]
+    Invalid { version: u64, source: SchemaError },
+    #[error("Schema version {version} was compiled, but could not be resolved.")]
+    Unresolvable { version: u64 },
+}
+
+/// Converts a Valico error into a diagnostic. Valico's error codes (ex. `wrong_type`) are used as the
+/// rule ID, with underscores swapped for dashes to match the rest of the rule IDs.
+fn diagnostic_from_valico(error: &dyn ValicoError) -> Diagnostic {
+    let message = match error.get_detail() {
+        Some(detail) => format!("{}: {}", error.get_title(), detail),
+        None => String::from(error.get_title()),
+    };
+    Diagnostic::error(error.get_path(), &error.get_code().replace('_', "-"), &message)
+}
+
+/// A version of a schema that has been compiled once and can then validate any number of values,
+/// including from several threads at once.
+pub struct CompiledSchema {
+    scope: Scope,
+    id: Url,
+    /// The schema as it was before compiling, used to look up the allowed values of an enum.
+    schema: Value,
+}
+
+impl CompiledSchema {
+    /// Compiles a schema. Fails if the schema isn't a valid JSON schema.
+    pub fn compile(version: &SchemaVersion, schema: Value) -> Result<CompiledSchema, CompileError> {
+        let mut scope = Scope::new();
+        let id = scope.compile(schema.clone(), false)
+            .map_err(|source| CompileError::Invalid { version: version.version, source })?;
+        if scope.resolve(&id).is_none() {
+            return Err(CompileError::Unresolvable { version: version.version });
+        }
+        Ok(CompiledSchema { scope, id, schema })
+    }
+
+    /// Validates a value against the schema, returning a diagnostic for every error found. Values that
+    /// aren't allowed by an enum get suggestions for the allowed values closest to them.
+    pub fn validate(&self, val: &Value) -> Vec<Diagnostic> {
+        // `compile()` already made sure the schema resolves.
+        let mut diagnostics: Vec<Diagnostic> = self.scope.resolve(&self.id)
+            .map(|schema| schema.validate(val).errors.iter().map(|error| diagnostic_from_valico(&**error)).collect())
+            .unwrap_or_default();
+        for diagnostic in diagnostics.iter_mut().filter(|diagnostic| diagnostic.rule == "enum") {
+            diagnostic.suggestions = suggest_enum(&self.schema, val, &diagnostic.pointer);
+        }
+        diagnostics
+    }
+}
+
+/// Wraps a validator so each version of its schema is only loaded and compiled once, no matter how many
+/// values are validated. Share it between tasks with an `Arc` to validate many files concurrently.
+pub struct CompiledValidator<V: Validator> {
+    validator: V,
+    schemas: Mutex<HashMap<u64, Arc<CompiledSchema>>>,
+}
+
+impl<V: Validator> CompiledValidator<V> {
+    pub fn new(validator: V) -> CompiledValidator<V> {
+        CompiledValidator { validator, schemas: Mutex::new(HashMap::new()) }
+    }
+}
+
+#[async_trait]
+impl<V: Validator + Send + Sync> Validator for CompiledValidator<V> {
+    fn versions(&self) -> &'static [SchemaVersion] {
+        self.validator.versions()
+    }
+
+    fn offline(&self) -> bool {
+        self.validator.offline()
+    }
+
+    fn ttl(&self) -> Duration {
+        self.validator.ttl()
+    }
+
+    fn store(&self) -> &dyn CacheStore {
+        self.validator.store()
+    }
+
+    async fn after_validate(&self, val: &Value, report: &mut ValidationReport) -> Result<()> {
+        self.validator.after_validate(val, report).await
+    }
+
+    async fn after_validate_file(&self, val: &Value, dir: &Path, report: &mut ValidationReport) -> Result<()> {
+        self.validator.after_validate_file(val, dir, report).await
+    }
+
+    /// Compiles a version the first time it's needed and reuses it after that. The lock is held while
+    /// compiling so concurrent validations don't download or compile the same version twice.
+    async fn compile(&self, version: &SchemaVersion) -> Result<Arc<CompiledSchema>> {
+        let mut schemas = self.schemas.lock().await;
+        if let Some(schema) = schemas.get(&version.version) {
+            return Ok(schema.clone());
+        }
+        let schema = self.validator.compile(version).await?;
+        schemas.insert(version.version, schema.clone());
+        Ok(schema)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use crate::cache::MemoryStore;
+
+    const GOOD: &[SchemaVersion] = &[SchemaVersion {
+        version: 1,
+        path: "good.min.json",
+        url: "https://example.com/good.json",
+        builtin: r#"{ "type": "object", "required": ["name"] }"#,
+        deprecated: false,
+    }];
+    const BAD: &[SchemaVersion] = &[SchemaVersion { builtin: "[]", ..GOOD[0] }];
+
+    /// A validator that only uses the built-in schema, and counts how many times it's asked for it.
+    struct Counting {
+        versions: &'static [SchemaVersion],
+        store: MemoryStore,
+        gets: AtomicUsize,
+    }
+
+    impl Counting {
+        fn new(versions: &'static [SchemaVersion]) -> Counting {
+            Counting { versions, store: MemoryStore::new(), gets: AtomicUsize::new(0) }
+        }
+    }
+
+    #[async_trait]
+    impl Validator for Counting {
+        fn versions(&self) -> &'static [SchemaVersion] {
+            self.versions
+        }
+
+        fn offline(&self) -> bool {
+            true
+        }
+
+        fn ttl(&self) -> Duration {
+            Duration::from_secs(0)
+        }
+
+        fn store(&self) -> &dyn CacheStore {
+            &self.store
+        }
+
+        async fn after_validate(&self, _val: &Value, _report: &mut ValidationReport) -> Result<()> {
+            Ok(())
+        }
+
+        /// Yields before returning, so concurrent validations all get a chance to ask at once.
+        async fn get(&self, version: &SchemaVersion) -> Result<Value> {
+            self.gets.fetch_add(1, Ordering::SeqCst);
+            let _ = tokio::task::yield_now().await;
+            Ok(serde_json::from_str(version.builtin)?)
+        }
+    }
+
+    #[test]
+    fn refuses_invalid_schemas() {
+        let err = CompiledSchema::compile(&BAD[0], serde_json::json!([])).err().unwrap();
+        assert!(matches!(err, CompileError::Invalid { version: 1, .. }));
+    }
+
+    #[tokio::test]
+    async fn fails_to_validate_with_invalid_schemas() {
+        let validator = CompiledValidator::new(Counting::new(BAD));
+        let err = validator.validate(&serde_json::json!({})).await.unwrap_err();
+        assert!(err.to_string().contains("Schema version 1 is not a valid JSON schema"));
+    }
+
+    #[tokio::test]
+    async fn compiles_each_version_once() {
+        let validator = Arc::new(CompiledValidator::new(Counting::new(GOOD)));
+        let tasks: Vec<_> = (0..8).map(|i| {
+            let validator = validator.clone();
+            tokio::spawn(async move {
+                let val = if i % 2 == 0 { serde_json::json!({ "name": "example" }) } else { serde_json::json!({}) };
+                validator.validate(&val).await.unwrap().is_valid()
+            })
+        }).collect();
+
+        let mut valid = Vec::new();
+        for task in tasks {
+            valid.push(task.await.unwrap());
+        }
+        assert_eq!(valid, vec![true, false, true, false, true, false, true, false]);
+        assert_eq!(validator.validator.gets.load(Ordering::SeqCst), 1);
+    }
+}
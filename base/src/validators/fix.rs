@@ -0,0 +1,290 @@
+//! Automatic repairs for common problems in ibis.json files. Only repairs that can't change what a package
+//! means are made: dangling and duplicate IDs are dropped, path separators are normalized, and the page
+//! table is sorted.
+//!
+//! Repairs are made as edits to the source of the file rather than by serializing it again, so everything
+//! else about it (key order, indentation, arrays kept on one line, line endings) stays the same.
+
+use anyhow::{Result, Context, anyhow};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use super::{SourceMap, pointer};
+
+/// A single repair made to a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Fix {
+    /// A JSON pointer to the value that was changed.
+    pub pointer: String,
+    /// A human-readable description of what was changed.
+    pub message: String,
+}
+
+/// The result of fixing a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixOutcome {
+    /// Every repair that was made. Empty if nothing needed fixing.
+    pub fixes: Vec<Fix>,
+    /// The fixed file. Identical to the original source if nothing needed fixing.
+    pub source: String,
+}
+
+/// How a repair changes the value its fix points to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Edit {
+    /// Removes the value from the array it's in.
+    Remove,
+    /// Replaces the value with this JSON.
+    Replace(String),
+    /// Sorts the members of the object by key.
+    Sort,
+}
+
+struct Repair {
+    fix: Fix,
+    edit: Edit,
+}
+
+/// Finds every ID that isn't in `known`, and every ID that's already been seen if `dedupe` is true.
+fn drop_ids(ids: &[Value], known: &HashSet<&str>, dedupe: bool, ids_pointer: &[&str]) -> Vec<Repair> {
+    let mut repairs = Vec::new();
+    let mut seen: HashSet<&str> = HashSet::new();
+
+    for (index, id) in ids.iter().enumerate() {
+        let id = match id.as_str() {
+            Some(id) => id,
+            None => continue,
+        };
+        let message = if !known.contains(id) {
+            format!("Removed `{}`, which is not the ID of a page.", id)
+        }
+        else if dedupe && !seen.insert(id) {
+            format!("Removed `{}`, which was listed more than once.", id)
+        }
+        else {
+            continue;
+        };
+        let at = pointer(&[ids_pointer, &[index.to_string().as_str()]].concat());
+        repairs.push(Repair { fix: Fix { pointer: at, message }, edit: Edit::Remove });
+    }
+
+    repairs
+}
+
+/// Finds every safe repair that can be made to a parsed ibis.json file.
+fn find_repairs(val: &Value) -> Vec<Repair> {
+    let mut repairs = Vec::new();
+    let empty = serde_json::Map::new();
+    let pages = val.get("pages").and_then(Value::as_object).unwrap_or(&empty);
+    let known: HashSet<&str> = pages.keys().map(String::as_str).collect();
+
+    if let Some(sidebar) = val.get("sidebar").and_then(Value::as_array) {
+        repairs.extend(drop_ids(sidebar, &known, false, &["sidebar"]));
+    }
+
+    for (id, page) in pages {
+        if let Some(children) = page.get("children").and_then(Value::as_array) {
+            repairs.extend(drop_ids(children, &known, true, &["pages", id, "children"]));
+        }
+
+        if let Some(old) = page.get("path").and_then(Value::as_str).filter(|old| old.contains('\\')) {
+            let new = old.replace('\\', "/");
+            repairs.push(Repair {
+                fix: Fix { pointer: pointer(&["pages", id, "path"]), message: format!("Changed `{}` to `{}`.", old, new) },
+                edit: Edit::Replace(Value::String(new).to_string()),
+            });
+        }
+    }
+
+    // The page table is in the order the pages are in the file, since serde_json keeps the order of keys.
+    let ids: Vec<&String> = pages.keys().collect();
+    if ids.windows(2).any(|pair| pair[0] > pair[1]) {
+        repairs.push(Repair {
+            fix: Fix { pointer: String::from("/pages"), message: String::from("Sorted the page table by page ID.") },
+            edit: Edit::Sort,
+        });
+    }
+
+    repairs
+}
+
+/// An element of an array or a member of an object in the source being edited.
+struct Member {
+    /// The member's key, or the element's index.
+    key: String,
+    pointer: String,
+    /// Where the member starts (at its key, for objects) and ends in the original source.
+    start: usize,
+    end: usize,
+    /// The member with the edits inside of it made.
+    text: String,
+}
+
+/// Applies edits to the source of a JSON file, copying everything they don't touch as is.
+struct Editor<'a> {
+    source: &'a str,
+    map: SourceMap<'a>,
+    edits: HashMap<&'a str, &'a Edit>,
+}
+
+impl<'a> Editor<'a> {
+    fn span(&self, at: &str) -> Result<(usize, usize)> {
+        self.map.span(at).context(format!("Could not find `{}` in the file.", at))
+    }
+
+    /// Returns true if an edit applies to the value at `at` or anything inside of it.
+    fn touches(&self, at: &str) -> bool {
+        self.edits.keys().any(|edit| edit.strip_prefix(at).is_some_and(|rest| rest.is_empty() || rest.starts_with('/')))
+    }
+
+    /// Gets the source of the value at `at` with every edit inside of it made.
+    fn render(&self, val: &Value, at: &str) -> Result<String> {
+        let (start, end) = self.span(at)?;
+        if !self.touches(at) {
+            return Ok(String::from(&self.source[start..end]));
+        }
+        if let Some(Edit::Replace(json)) = self.edits.get(at) {
+            return Ok(json.clone());
+        }
+
+        let mut members = match val {
+            Value::Array(items) => items.iter().enumerate().map(|(index, item)| {
+                let child = format!("{}/{}", at, index);
+                let (start, end) = self.span(&child)?;
+                Ok(Member { key: index.to_string(), text: self.render(item, &child)?, pointer: child, start, end })
+            }).collect::<Result<Vec<_>>>()?,
+            Value::Object(object) => object.iter().map(|(key, item)| {
+                let child = format!("{}{}", at, pointer(&[key]));
+                let (value_start, end) = self.span(&child)?;
+                let start = self.map.key_start(&child).context(format!("Could not find `{}` in the file.", child))?;
+                let text = format!("{}{}", &self.source[start..value_start], self.render(item, &child)?);
+                Ok(Member { key: key.clone(), pointer: child, start, end, text })
+            }).collect::<Result<Vec<_>>>()?,
+            _ => return Ok(String::from(&self.source[start..end])),
+        };
+        members.sort_by_key(|member| member.start);
+        Ok(self.join(start, end, members, self.edits.get(at) == Some(&&Edit::Sort)))
+    }
+
+    /// Puts an array or object back together from its members, leaving out removed ones and sorting them
+    /// if `sort` is true. The whitespace and separators between members are kept where they were.
+    fn join(&self, start: usize, end: usize, mut members: Vec<Member>, sort: bool) -> String {
+        let (open, close) = (&self.source[start..start + 1], &self.source[end - 1..end]);
+        let (first, last) = match (members.first(), members.last()) {
+            (Some(first), Some(last)) => (first.start, last.end),
+            _ => return String::from(&self.source[start..end]),
+        };
+        let separators: Vec<&str> = members.windows(2).map(|pair| &self.source[pair[0].end..pair[1].start]).collect();
+
+        members.retain(|member| self.edits.get(member.pointer.as_str()) != Some(&&Edit::Remove));
+        if members.is_empty() {
+            return format!("{}{}", open, close);
+        }
+        if sort {
+            members.sort_by(|a, b| a.key.cmp(&b.key));
+        }
+
+        let mut out = format!("{}{}", open, &self.source[start + 1..first]);
+        for (index, member) in members.iter().enumerate() {
+            if index > 0 {
+                out.push_str(separators[index - 1]);
+            }
+            out.push_str(&member.text);
+        }
+        out.push_str(&self.source[last..end - 1]);
+        out.push_str(close);
+        out
+    }
+}
+
+/// Fixes the source of an ibis.json file. The only changes made are the repairs themselves. Fails if the
+/// source isn't valid JSON, or if there's something to repair but an object in the file has the same key
+/// more than once, since the repairs can't tell which of the members they're for.
+pub fn fix_source(source: &str) -> Result<FixOutcome> {
+    let val: Value = serde_json::from_str(source).context("Could not parse file to fix.")?;
+    let repairs = find_repairs(&val);
+    if repairs.is_empty() {
+        return Ok(FixOutcome { fixes: Vec::new(), source: String::from(source) });
+    }
+
+    let map = SourceMap::new(source);
+    if let Some(duplicate) = map.duplicate_keys().first() {
+        return Err(anyhow!(
+            "`{}` is in the file more than once, so it can't be fixed automatically. Remove the duplicate and try again.",
+            duplicate
+        ));
+    }
+
+    let fixed = {
+        let editor = Editor {
+            source,
+            map,
+            edits: repairs.iter().map(|repair| (repair.fix.pointer.as_str(), &repair.edit)).collect(),
+        };
+        let (start, end) = editor.span("")?;
+        format!("{}{}{}", &source[..start], editor.render(&val, "")?, &source[end..])
+    };
+    Ok(FixOutcome { fixes: repairs.into_iter().map(|repair| repair.fix).collect(), source: fixed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed(source: &str) -> (Vec<String>, String) {
+        let outcome = fix_source(source).unwrap();
+        (outcome.fixes.into_iter().map(|fix| fix.pointer).collect(), outcome.source)
+    }
+
+    #[test]
+    fn drops_unknown_sidebar_ids() {
+        let source = "{\n  \"pages\": { \"a\": {}, \"b\": {} },\n  \"sidebar\": [\"a\", \"gone\", \"b\"]\n}\n";
+        assert_eq!(fixed(source), (
+            vec![String::from("/sidebar/1")],
+            String::from("{\n  \"pages\": { \"a\": {}, \"b\": {} },\n  \"sidebar\": [\"a\", \"b\"]\n}\n"),
+        ));
+    }
+
+    #[test]
+    fn drops_unknown_and_duplicate_children() {
+        let source = "{\"pages\": {\n    \"a\": {\"children\": [\n        \"b\",\n        \"gone\",\n        \"b\"\n    ]},\n    \"b\": {\"children\": [\"gone\"]}\n}}";
+        assert_eq!(fixed(source), (
+            vec![String::from("/pages/a/children/1"), String::from("/pages/a/children/2"), String::from("/pages/b/children/0")],
+            String::from("{\"pages\": {\n    \"a\": {\"children\": [\n        \"b\"\n    ]},\n    \"b\": {\"children\": []}\n}}"),
+        ));
+    }
+
+    #[test]
+    fn normalizes_path_separators() {
+        let source = r#"{ "pages": { "a": { "path": "guide\\a.html", "title": "A" } } }"#;
+        assert_eq!(fixed(source), (
+            vec![String::from("/pages/a/path")],
+            String::from(r#"{ "pages": { "a": { "path": "guide/a.html", "title": "A" } } }"#),
+        ));
+    }
+
+    #[test]
+    fn sorts_the_page_table() {
+        let source = "{\r\n\t\"pages\": {\r\n\t\t\"b\": { \"children\": [\"a\", \"a\"] },\r\n\t\t\"a\": {}\r\n\t}\r\n}";
+        assert_eq!(fixed(source), (
+            vec![String::from("/pages/b/children/1"), String::from("/pages")],
+            String::from("{\r\n\t\"pages\": {\r\n\t\t\"a\": {},\r\n\t\t\"b\": { \"children\": [\"a\"] }\r\n\t}\r\n}"),
+        ));
+    }
+
+    #[test]
+    fn refuses_duplicate_keys() {
+        let source = r#"{ "pages": { "b": { "children": ["gone"] }, "a": {}, "b": {} }, "sidebar": ["a"] }"#;
+        let err = fix_source(source).unwrap_err();
+        assert_eq!(err.to_string(), "`/pages/b` is in the file more than once, so it can't be fixed automatically. Remove the duplicate and try again.");
+
+        let source = r#"{ "pages": { "a": {}, "a": {} }, "sidebar": ["a"] }"#;
+        assert_eq!(fixed(source), (vec![], String::from(source)));
+    }
+
+    #[test]
+    fn leaves_correct_files_alone() {
+        let source = "{ \"pages\": { \"a\": {} },   \"sidebar\": [ \"a\" ] }\r\n";
+        assert_eq!(fixed(source), (vec![], String::from(source)));
+    }
+}
@@ -0,0 +1,241 @@
+//! Lint rules for ibis.json files. These check constraints that the schema documents but can't enforce,
+//! plus a few common mistakes. Every problem found is reported as a warning, and every rule can be turned
+//! off by adding its ID to `lint.disabled` in the config.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use crate::models::PackageManifest;
+use super::{Diagnostic, ValidationReport, normalize_path, pointer};
+
+/// A lint rule that can be turned off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LintRule {
+    pub id: &'static str,
+    pub description: &'static str,
+}
+
+pub const NAME_CHARACTERS: LintRule = LintRule {
+    id: "name-characters",
+    description: "The package name may only contain letters, slashes, dashes, and underscores.",
+};
+pub const TITLE_CHARACTERS: LintRule = LintRule {
+    id: "title-characters",
+    description: "Page titles may only contain letters, numbers, dashes, underscores, and spaces.",
+};
+pub const EMPTY_TITLE: LintRule = LintRule {
+    id: "empty-title",
+    description: "Page titles can't be empty.",
+};
+pub const LANGUAGE_CODE: LintRule = LintRule {
+    id: "language-code",
+    description: "The package language must be an ISO 639-1 language code.",
+};
+pub const DUPLICATE_PAGE_PATH: LintRule = LintRule {
+    id: "duplicate-page-path",
+    description: "No two pages may have the same path.",
+};
+pub const UNREACHABLE_PAGE: LintRule = LintRule {
+    id: "unreachable-page",
+    description: "Every page should be reachable from the sidebar, either directly or as a child.",
+};
+
+/// Every lint rule.
+pub const LINT_RULES: &[LintRule] = &[
+    NAME_CHARACTERS,
+    TITLE_CHARACTERS,
+    EMPTY_TITLE,
+    LANGUAGE_CODE,
+    DUPLICATE_PAGE_PATH,
+    UNREACHABLE_PAGE,
+];
+
+/// Every ISO 639-1 language code.
+const LANGUAGE_CODES: &[&str] = &[
+    "aa", "ab", "ae", "af", "ak", "am", "an", "ar", "as", "av", "ay", "az", "ba", "be", "bg", "bh", "bi",
+    "bm", "bn", "bo", "br", "bs", "ca", "ce", "ch", "co", "cr", "cs", "cu", "cv", "cy", "da", "de", "dv",
+    "dz", "ee", "el", "en", "eo", "es", "et", "eu", "fa", "ff", "fi", "fj", "fo", "fr", "fy", "ga", "gd",
+    "gl", "gn", "gu", "gv", "ha", "he", "hi", "ho", "hr", "ht", "hu", "hy", "hz", "ia", "id", "ie", "ig",
+    "ii", "ik", "io", "is", "it", "iu", "ja", "jv", "ka", "kg", "ki", "kj", "kk", "kl", "km", "kn", "ko",
+    "kr", "ks", "ku", "kv", "kw", "ky", "la", "lb", "lg", "li", "ln", "lo", "lt", "lu", "lv", "mg", "mh",
+    "mi", "mk", "ml", "mn", "mr", "ms", "mt", "my", "na", "nb", "nd", "ne", "ng", "nl", "nn", "no", "nr",
+    "nv", "ny", "oc", "oj", "om", "or", "os", "pa", "pi", "pl", "ps", "pt", "qu", "rm", "rn", "ro", "ru",
+    "rw", "sa", "sc", "sd", "se", "sg", "si", "sk", "sl", "sm", "sn", "so", "sq", "sr", "ss", "st", "su",
+    "sv", "sw", "ta", "te", "tg", "th", "ti", "tk", "tl", "tn", "to", "tr", "ts", "tt", "tw", "ty", "ug",
+    "uk", "ur", "uz", "ve", "vi", "vo", "wa", "wo", "xh", "yi", "yo", "za", "zh", "zu",
+];
+
+/// Configuration for the lint rules.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LintConfig {
+    /// The IDs of the rules that shouldn't be run.
+    pub disabled: Vec<String>,
+}
+
+impl LintConfig {
+    /// Returns true if the rule hasn't been turned off.
+    pub fn is_enabled(&self, rule: &LintRule) -> bool {
+        !self.disabled.iter().any(|id| id == rule.id)
+    }
+
+    /// Gets the IDs in `disabled` that aren't the ID of any rule, ex. because of a typo.
+    pub fn unknown_rules(&self) -> Vec<&str> {
+        self.disabled.iter()
+            .map(String::as_str)
+            .filter(|id| !LINT_RULES.iter().any(|rule| rule.id == *id))
+            .collect()
+    }
+}
+
+/// Adds a warning for the rule to the report if the rule is enabled.
+fn warn(config: &LintConfig, report: &mut ValidationReport, rule: &LintRule, pointer: &str, message: &str) {
+    if config.is_enabled(rule) {
+        report.push(Diagnostic::warning(pointer, rule.id, message));
+    }
+}
+
+fn check_name(manifest: &PackageManifest, config: &LintConfig, report: &mut ValidationReport) {
+    let name = &manifest.name;
+    if !name.chars().all(|c| c.is_ascii_alphabetic() || c == '/' || c == '-' || c == '_') {
+        warn(config, report, &NAME_CHARACTERS, "/name",
+            &format!("The package name `{}` may only contain letters, slashes, dashes, and underscores.", name));
+    }
+}
+
+fn check_language(manifest: &PackageManifest, config: &LintConfig, report: &mut ValidationReport) {
+    if let Some(language) = manifest.language.as_deref() {
+        if !LANGUAGE_CODES.contains(&language) {
+            warn(config, report, &LANGUAGE_CODE, "/language",
+                &format!("`{}` is not an ISO 639-1 language code (ex. `en`).", language));
+        }
+    }
+}
+
+fn check_titles(manifest: &PackageManifest, config: &LintConfig, report: &mut ValidationReport) {
+    for (id, page) in &manifest.pages {
+        let title = &page.title;
+        let title_pointer = pointer(&["pages", id, "title"]);
+        if title.trim().is_empty() {
+            warn(config, report, &EMPTY_TITLE, &title_pointer, &format!("Page `{}` has an empty title.", id));
+        }
+        else if !title.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == ' ') {
+            warn(config, report, &TITLE_CHARACTERS, &title_pointer,
+                &format!("The title of page `{}` may only contain letters, numbers, dashes, underscores, and spaces.", id));
+        }
+    }
+}
+
+fn check_duplicate_paths(manifest: &PackageManifest, config: &LintConfig, report: &mut ValidationReport) {
+    let mut by_path: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for (id, page) in &manifest.pages {
+        by_path.entry(normalize_path(&page.path)).or_default().push(id);
+    }
+
+    for (path, ids) in by_path {
+        if let [_, duplicates @ ..] = &ids[..] {
+            for id in duplicates {
+                warn(config, report, &DUPLICATE_PAGE_PATH, &pointer(&["pages", id, "path"]),
+                    &format!("Page `{}` has the path `{}`, which is also used by page `{}`.", id, path, ids[0]));
+            }
+        }
+    }
+}
+
+fn check_reachable(manifest: &PackageManifest, config: &LintConfig, report: &mut ValidationReport) {
+    let mut reached: HashSet<&str> = HashSet::new();
+    let mut queue: VecDeque<&str> = manifest.sidebar.iter().map(String::as_str).collect();
+
+    while let Some(id) = queue.pop_front() {
+        if !reached.insert(id) {
+            continue;
+        }
+        if let Some(page) = manifest.pages.get(id) {
+            queue.extend(page.children.iter().map(String::as_str));
+        }
+    }
+
+    for id in manifest.pages.keys() {
+        if !reached.contains(id.as_str()) {
+            warn(config, report, &UNREACHABLE_PAGE, &pointer(&["pages", id]),
+                &format!("Page `{}` can't be reached from the sidebar, so it will only show up in search.", id));
+        }
+    }
+}
+
+/// Runs every enabled lint rule against a manifest that has already passed schema validation.
+pub fn lint(manifest: &PackageManifest, config: &LintConfig, report: &mut ValidationReport) {
+    check_name(manifest, config, report);
+    check_language(manifest, config, report);
+    check_titles(manifest, config, report);
+    check_duplicate_paths(manifest, config, report);
+    check_reachable(manifest, config, report);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lints a package with the given name, language, and pages, where only the first page is in the
+    /// sidebar, and returns the rule and pointer of every warning.
+    fn warnings(name: &str, language: &str, pages: &str, config: &LintConfig) -> Vec<(String, String)> {
+        let manifest = PackageManifest::from_json(&format!(
+            r#"{{ "name": "{}", "version": "1.0.0", "language": "{}", "pages": {}, "sidebar": ["a"] }}"#,
+            name, language, pages
+        )).unwrap();
+        let mut report = ValidationReport::default();
+        lint(&manifest, config, &mut report);
+        report.diagnostics.into_iter().map(|diagnostic| (diagnostic.rule, diagnostic.pointer)).collect()
+    }
+
+    fn page(title: &str, path: &str, children: &[&str]) -> String {
+        format!(r#"{{ "title": "{}", "path": "{}", "entryType": "guide", "children": {:?} }}"#, title, path, children)
+    }
+
+    fn warning(rule: &LintRule, pointer: &str) -> (String, String) {
+        (String::from(rule.id), String::from(pointer))
+    }
+
+    #[test]
+    fn accepts_clean_packages() {
+        let pages = format!(r#"{{ "a": {}, "b": {} }}"#, page("Getting started", "a.html", &["b"]), page("Guide_2", "b.html", &[]));
+        assert_eq!(warnings("rust/std-lib", "en", &pages, &LintConfig::default()), vec![]);
+    }
+
+    #[test]
+    fn checks_names_and_languages() {
+        let pages = format!(r#"{{ "a": {} }}"#, page("A", "a.html", &[]));
+        assert_eq!(warnings("rust 1.0", "english", &pages, &LintConfig::default()), vec![
+            warning(&NAME_CHARACTERS, "/name"),
+            warning(&LANGUAGE_CODE, "/language"),
+        ]);
+    }
+
+    #[test]
+    fn checks_titles() {
+        let pages = format!(r#"{{ "a": {}, "b": {} }}"#, page(" ", "a.html", &["b"]), page("What's new?", "b.html", &[]));
+        assert_eq!(warnings("rust", "en", &pages, &LintConfig::default()), vec![
+            warning(&EMPTY_TITLE, "/pages/a/title"),
+            warning(&TITLE_CHARACTERS, "/pages/b/title"),
+        ]);
+    }
+
+    #[test]
+    fn finds_duplicate_paths() {
+        let pages = format!(r#"{{ "a": {}, "b": {} }}"#, page("A", "guide/a.html", &["b"]), page("B", "./guide\\\\a.html", &[]));
+        assert_eq!(warnings("rust", "en", &pages, &LintConfig::default()), vec![warning(&DUPLICATE_PAGE_PATH, "/pages/b/path")]);
+    }
+
+    #[test]
+    fn finds_unreachable_pages() {
+        let pages = format!(r#"{{ "a": {}, "b": {}, "c": {} }}"#, page("A", "a.html", &["b"]), page("B", "b.html", &[]), page("C", "c.html", &[]));
+        assert_eq!(warnings("rust", "en", &pages, &LintConfig::default()), vec![warning(&UNREACHABLE_PAGE, "/pages/c")]);
+    }
+
+    #[test]
+    fn skips_disabled_rules() {
+        let pages = format!(r#"{{ "a": {}, "c": {} }}"#, page("A", "a.html", &[]), page("C", "c.html", &[]));
+        let config = LintConfig { disabled: vec![String::from("unreachable-page"), String::from("unreachable-pages")] };
+        assert_eq!(warnings("rust", "en", &pages, &config), vec![]);
+        assert_eq!(config.unknown_rules(), vec!["unreachable-pages"]);
+    }
+}
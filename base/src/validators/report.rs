@@ -0,0 +1,154 @@
+//! Typed results of validating a JSON file. Every problem found while validating is stored as a
+//! `Diagnostic` so callers can act on individual problems instead of parsing a formatted string.
+
+use serde::Serialize;
+
+/// How serious a diagnostic is. Any `Error` makes the validated file invalid; `Warning`s don't.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Where a value is in the source of a validated file. Lines and columns start at 1, and columns are
+/// counted in characters. The end is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+/// A single problem found while validating a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// A JSON pointer (RFC 6901) to the value the problem was found at, ex. `/pages/foo/entryType`.
+    pub pointer: String,
+    /// The ID of the rule that found the problem, ex. `enum` or `unknown-child`.
+    pub rule: String,
+    /// A human-readable description of the problem.
+    pub message: String,
+    pub severity: Severity,
+    /// Where the value the problem was found at is in the file. Only set by `Validator::validate_file()`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+    /// Values that were probably meant instead of the one found, closest first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<String>,
+}
+
+impl Diagnostic {
+    /// Creates a new diagnostic with the `Error` severity.
+    pub fn error(pointer: &str, rule: &str, message: &str) -> Diagnostic {
+        Diagnostic {
+            pointer: String::from(pointer),
+            rule: String::from(rule),
+            message: String::from(message),
+            severity: Severity::Error,
+            location: None,
+            suggestions: Vec::new(),
+        }
+    }
+
+    /// Creates a new diagnostic with the `Warning` severity.
+    pub fn warning(pointer: &str, rule: &str, message: &str) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(pointer, rule, message)
+        }
+    }
+}
+
+/// Every diagnostic found while validating a single file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ValidationReport {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ValidationReport {
+    pub fn new() -> ValidationReport {
+        ValidationReport::default()
+    }
+
+    /// Adds a diagnostic to the report.
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Returns true if none of the diagnostics in the report are errors.
+    pub fn is_valid(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    /// Iterates over the diagnostics with the `Error` severity.
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter().filter(|d| d.severity == Severity::Error)
+    }
+
+    /// Iterates over the diagnostics with the `Warning` severity.
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter().filter(|d| d.severity == Severity::Warning)
+    }
+}
+
+/// Builds a JSON pointer out of a list of keys, escaping `~` and `/` in each one.
+/// `pointer(&["pages", "foo", "children"])` returns `/pages/foo/children`.
+pub fn pointer(tokens: &[&str]) -> String {
+    tokens.iter()
+        .map(|token| format!("/{}", token.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+    use crate::cache::MemoryStore;
+    use crate::validators::{PackageSchema, Validator};
+
+    #[test]
+    fn escapes_pointers() {
+        assert_eq!(pointer(&["pages", "a/b~c", "children"]), "/pages/a~1b~0c/children");
+        assert_eq!(pointer(&[]), "");
+    }
+
+    #[tokio::test]
+    async fn reports_every_problem_in_a_file() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a.html"), "<p>A</p>").unwrap();
+        std::fs::write(dir.path().join("ibis.json"), concat!(
+            "{\n",
+            "  \"name\": \"example\",\n",
+            "  \"version\": \"1.0.0\",\n",
+            "  \"pages\": {\n",
+            "    \"a\": { \"title\": \"A\", \"path\": \"a.html\", \"entryType\": \"guide\", \"children\": [\"gone\"] },\n",
+            "    \"b\": { \"title\": \"B\", \"path\": \"b.html\", \"entryType\": \"guide\" }\n",
+            "  },\n",
+            "  \"sidebar\": [\"a\"]\n",
+            "}\n",
+        )).unwrap();
+        let schema = PackageSchema { offline: true, store: Arc::new(MemoryStore::new()), ..PackageSchema::default() };
+
+        let report = schema.validate_file(&dir.path().join("ibis.json").to_string_lossy()).await.unwrap();
+        let at = |line, column, end_column| Some(Location { line, column, end_line: line, end_column });
+        assert_eq!(report.diagnostics, vec![
+            Diagnostic {
+                location: at(5, 79, 85),
+                ..Diagnostic::error("/pages/a/children/0", "unknown-child", "Page `a` lists `gone` as a child, but there is no page with that ID.")
+            },
+            Diagnostic {
+                location: at(6, 10, 66),
+                ..Diagnostic::warning("/pages/b", "unreachable-page", "Page `b` can't be reached from the sidebar, so it will only show up in search.")
+            },
+            Diagnostic {
+                location: at(6, 34, 42),
+                ..Diagnostic::error("/pages/b/path", "missing-page-file", "Page `b` has the path `b.html`, but no file exists there.")
+            },
+        ]);
+        assert!(!report.is_valid());
+        assert_eq!((report.errors().count(), report.warnings().count()), (2, 1));
+    }
+}
@@ -0,0 +1,223 @@
+//! Maps JSON pointers back to where their values are in the source of a JSON file, so diagnostics can be
+//! shown with a line and column. serde_json doesn't keep track of where values came from, so the source is
+//! scanned separately after it's been parsed.
+
+use std::collections::HashMap;
+use super::{Location, pointer};
+
+/// Where every value in a JSON file is, as byte offsets into the source.
+pub struct SourceMap<'a> {
+    source: &'a str,
+    spans: HashMap<String, (usize, usize)>,
+    keys: HashMap<String, usize>,
+    duplicates: Vec<String>,
+    line_starts: Vec<usize>,
+}
+
+/// A minimal JSON scanner that records the span of every value it passes, where the key of every object
+/// member starts, and which members have a key that was already used in the same object. It assumes the source has already been parsed successfully by serde_json, and
+/// stops early (keeping what it found so far) if not.
+struct Scanner<'a> {
+    src: &'a [u8],
+    pos: usize,
+    spans: HashMap<String, (usize, usize)>,
+    keys: HashMap<String, usize>,
+    duplicates: Vec<String>,
+}
+
+impl<'a> Scanner<'a> {
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.src.len() && self.src[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    /// Moves past the byte at the current position if it's `byte`.
+    fn eat(&mut self, byte: u8) -> bool {
+        self.skip_whitespace();
+        if self.src.get(self.pos) == Some(&byte) {
+            self.pos += 1;
+            true
+        }
+        else {
+            false
+        }
+    }
+
+    /// Moves past a string, returning its raw source (including quotes).
+    fn string(&mut self) -> Option<&'a str> {
+        let start = self.pos;
+        self.pos += 1;
+        while self.pos < self.src.len() {
+            match self.src[self.pos] {
+                b'\\' => self.pos += 2,
+                b'"' => {
+                    self.pos += 1;
+                    return std::str::from_utf8(&self.src[start..self.pos]).ok();
+                }
+                _ => self.pos += 1,
+            }
+        }
+        None
+    }
+
+    fn value(&mut self, at: String) -> Option<()> {
+        self.skip_whitespace();
+        let start = self.pos;
+        match self.src.get(self.pos)? {
+            b'{' => {
+                self.pos += 1;
+                if !self.eat(b'}') {
+                    loop {
+                        self.skip_whitespace();
+                        let key_start = self.pos;
+                        let raw_key = self.string()?;
+                        let key: String = serde_json::from_str(raw_key).ok()?;
+                        if !self.eat(b':') { return None; }
+                        let member = format!("{}{}", at, pointer(&[&key]));
+                        if self.keys.insert(member.clone(), key_start).is_some() {
+                            self.duplicates.push(member.clone());
+                        }
+                        self.value(member)?;
+                        if self.eat(b'}') { break; }
+                        if !self.eat(b',') { return None; }
+                    }
+                }
+            }
+            b'[' => {
+                self.pos += 1;
+                if !self.eat(b']') {
+                    let mut index = 0;
+                    loop {
+                        self.value(format!("{}/{}", at, index))?;
+                        index += 1;
+                        if self.eat(b']') { break; }
+                        if !self.eat(b',') { return None; }
+                    }
+                }
+            }
+            b'"' => {
+                self.string()?;
+            }
+            _ => {
+                while self.pos < self.src.len() && !b",}] \t\r\n".contains(&self.src[self.pos]) {
+                    self.pos += 1;
+                }
+            }
+        }
+        self.spans.insert(at, (start, self.pos));
+        Some(())
+    }
+}
+
+impl<'a> SourceMap<'a> {
+    /// Scans the source of a JSON file for the location of every value in it.
+    pub fn new(source: &'a str) -> SourceMap<'a> {
+        let mut scanner = Scanner { src: source.as_bytes(), pos: 0, spans: HashMap::new(), keys: HashMap::new(), duplicates: Vec::new() };
+        scanner.value(String::new());
+
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        SourceMap { source, spans: scanner.spans, keys: scanner.keys, duplicates: scanner.duplicates, line_starts }
+    }
+
+    /// Gets the byte offsets the value a JSON pointer points to starts and ends at.
+    pub fn span(&self, at: &str) -> Option<(usize, usize)> {
+        self.spans.get(at).copied()
+    }
+
+    /// Gets the byte offset the key of an object member starts at, given a JSON pointer to its value.
+    pub fn key_start(&self, at: &str) -> Option<usize> {
+        self.keys.get(at).copied()
+    }
+
+    /// Gets a JSON pointer to every object member whose key was already used in the same object, in the
+    /// order they're in the file. Spans and key starts are only kept for the last member with a key.
+    pub fn duplicate_keys(&self) -> &[String] {
+        &self.duplicates
+    }
+
+    /// Converts a byte offset into a 1-based line and column (counted in characters).
+    fn line_column(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|start| *start <= offset);
+        let line_start = self.line_starts[line - 1];
+        let column = self.source[line_start..offset].chars().count() + 1;
+        (line, column)
+    }
+
+    /// Finds the location of the value a JSON pointer points to. If there's no value there (ex. for a
+    /// missing required field), finds the location of the closest parent that does exist.
+    pub fn locate(&self, at: &str) -> Option<Location> {
+        let mut at = at;
+        loop {
+            if let Some(&(start, end)) = self.spans.get(at) {
+                let (line, column) = self.line_column(start);
+                let (end_line, end_column) = self.line_column(end);
+                return Some(Location { line, column, end_line, end_column });
+            }
+            at = &at[..at.rfind('/')?];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gets the line and column a value starts at and ends at.
+    fn locate(source: &str, at: &str) -> Option<(usize, usize, usize, usize)> {
+        serde_json::from_str::<serde_json::Value>(source).unwrap();
+        SourceMap::new(source).locate(at).map(|location| (location.line, location.column, location.end_line, location.end_column))
+    }
+
+    #[test]
+    fn locates_nested_values() {
+        let source = "{\n  \"pages\": {\n    \"a\": { \"title\": \"A\" }\n  }\n}";
+        assert_eq!(locate(source, "/pages/a/title"), Some((3, 21, 3, 24)));
+        assert_eq!(locate(source, "/pages/a"), Some((3, 10, 3, 26)));
+        assert_eq!(locate(source, "/pages"), Some((2, 12, 4, 4)));
+        assert_eq!(locate(source, ""), Some((1, 1, 5, 2)));
+    }
+
+    #[test]
+    fn locates_array_elements() {
+        let source = "{ \"sidebar\": [\"a\", [1, 2], {}] }";
+        assert_eq!(locate(source, "/sidebar/0"), Some((1, 15, 1, 18)));
+        assert_eq!(locate(source, "/sidebar/1/1"), Some((1, 24, 1, 25)));
+        assert_eq!(locate(source, "/sidebar/2"), Some((1, 28, 1, 30)));
+    }
+
+    #[test]
+    fn locates_escaped_keys() {
+        let source = r#"{ "a~b": 1, "c/d": 2, "e\"f": 3, "gh": 4 }"#;
+        assert_eq!(locate(source, "/a~0b"), Some((1, 10, 1, 11)));
+        assert_eq!(locate(source, "/c~1d"), Some((1, 20, 1, 21)));
+        assert_eq!(locate(source, "/e\"f"), Some((1, 31, 1, 32)));
+        assert_eq!(locate(source, "/gh"), Some((1, 40, 1, 41)));
+        assert_eq!(SourceMap::new(source).key_start("/c~1d"), Some(12));
+    }
+
+    #[test]
+    fn counts_columns_in_characters() {
+        let source = "{ \"tïtlé\": \"日本\", \"next\": true }";
+        assert_eq!(locate(source, "/tïtlé"), Some((1, 12, 1, 16)));
+        assert_eq!(locate(source, "/next"), Some((1, 26, 1, 30)));
+    }
+
+    #[test]
+    fn handles_crlf_line_endings() {
+        let source = "{\r\n  \"a\": 1,\r\n  \"b\": [\r\n    true\r\n  ]\r\n}\r\n";
+        assert_eq!(locate(source, "/a"), Some((2, 8, 2, 9)));
+        assert_eq!(locate(source, "/b/0"), Some((4, 5, 4, 9)));
+        assert_eq!(locate(source, "/b"), Some((3, 8, 5, 4)));
+    }
+
+    #[test]
+    fn falls_back_to_the_closest_parent() {
+        let source = "{ \"pages\": { \"a\": {} } }";
+        assert_eq!(locate(source, "/pages/a/title"), locate(source, "/pages/a"));
+        assert_eq!(locate(source, "/missing"), locate(source, ""));
+    }
+}
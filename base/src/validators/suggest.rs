@@ -0,0 +1,135 @@
+//! "Did you mean" suggestions for values that aren't one of the values allowed by an `enum` in the schema.
+
+use serde_json::Value;
+
+/// The most suggestions given for a single value.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Splits a JSON pointer into its unescaped tokens.
+fn tokens(pointer: &str) -> impl Iterator<Item = String> + '_ {
+    pointer.split('/').skip(1).map(|token| token.replace("~1", "/").replace("~0", "~"))
+}
+
+/// Follows a local `$ref` (ex. `#/definitions/EntryTypes`) if the schema has one.
+fn resolve<'a>(root: &'a Value, schema: &'a Value) -> Option<&'a Value> {
+    match schema.get("$ref").and_then(Value::as_str) {
+        Some(reference) => root.pointer(reference.strip_prefix('#')?),
+        None => Some(schema),
+    }
+}
+
+/// Finds the part of a schema that applies to the value at a JSON pointer. Only follows `properties`,
+/// `additionalProperties`, `items`, and local `$ref`s, which is everything the Ibis schemas use.
+fn schema_at<'a>(root: &'a Value, pointer: &str) -> Option<&'a Value> {
+    let mut schema = resolve(root, root)?;
+    for token in tokens(pointer) {
+        let next = match schema.get("properties").and_then(|properties| properties.get(&token)) {
+            Some(property) => property,
+            None => schema.get("additionalProperties")
+                .filter(|additional| additional.is_object())
+                .or_else(|| schema.get("items"))?,
+        };
+        schema = resolve(root, next)?;
+    }
+    Some(schema)
+}
+
+/// The number of single character insertions, deletions, and substitutions it takes to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + if a_char == *b_char { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Ranks the allowed values by how close they are to `value`, ignoring case, and returns the closest
+/// ones. Values that are too different to be a likely typo aren't suggested.
+pub fn closest<'a>(value: &str, allowed: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let value = value.to_lowercase();
+    let threshold = (value.chars().count() / 3).max(1);
+    let mut ranked: Vec<(usize, &str)> = allowed
+        .map(|candidate| (edit_distance(&value, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= threshold)
+        .collect();
+    ranked.sort();
+    ranked.into_iter().take(MAX_SUGGESTIONS).map(|(_, candidate)| String::from(candidate)).collect()
+}
+
+/// Suggests values for the value at a JSON pointer that failed an `enum` check in the schema.
+pub fn suggest_enum(schema: &Value, val: &Value, pointer: &str) -> Vec<String> {
+    let value = match val.pointer(pointer).and_then(Value::as_str) {
+        Some(value) => value,
+        None => return Vec::new(),
+    };
+    match schema_at(schema, pointer).and_then(|schema| schema.get("enum")).and_then(Value::as_array) {
+        Some(allowed) => closest(value, allowed.iter().filter_map(Value::as_str)),
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use serde_json::json;
+    use crate::cache::MemoryStore;
+    use crate::constants::BUILTIN_SCHEMA;
+    use crate::validators::{PackageSchema, Validator};
+
+    fn manifest(entry_type: &str) -> Value {
+        json!({
+            "name": "example",
+            "version": "1.0.0",
+            "pages": { "a": { "title": "A", "path": "a.html", "entryType": entry_type } },
+            "sidebar": ["a"],
+        })
+    }
+
+    #[test]
+    fn measures_edit_distance() {
+        assert_eq!(edit_distance("function", "function"), 0);
+        assert_eq!(edit_distance("fucntion", "function"), 2);
+        assert_eq!(edit_distance("attibute", "attribute"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("日本", "日本語"), 1);
+    }
+
+    #[test]
+    fn suggests_close_values() {
+        let allowed = ["function", "fixture", "field", "class"];
+        assert_eq!(closest("Function", allowed.iter().copied()), vec!["function"]);
+        assert_eq!(closest("fild", allowed.iter().copied()), vec!["field"]);
+        // Values can be a third of their length away, rounded down, but at least one edit away.
+        assert_eq!(closest("feild", allowed.iter().copied()), Vec::<String>::new());
+        assert_eq!(closest("functoin", allowed.iter().copied()), vec!["function"]);
+        assert_eq!(closest("cl", allowed.iter().copied()), Vec::<String>::new());
+        assert_eq!(closest("CLASS", ["clasp", "glass", "Class"].iter().copied()), vec!["Class", "clasp", "glass"]);
+        assert_eq!(closest("gizmo", allowed.iter().copied()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn suggests_entry_types() {
+        let schema: Value = serde_json::from_str(BUILTIN_SCHEMA).unwrap();
+        assert_eq!(suggest_enum(&schema, &manifest("Function"), "/pages/a/entryType"), vec!["function"]);
+        assert_eq!(suggest_enum(&schema, &manifest("widget-thing"), "/pages/a/entryType"), Vec::<String>::new());
+        assert_eq!(suggest_enum(&schema, &manifest("Function"), "/name"), Vec::<String>::new());
+    }
+
+    #[tokio::test]
+    async fn accepts_both_spellings_of_attribute() {
+        let schema = PackageSchema { offline: true, store: Arc::new(MemoryStore::new()), ..PackageSchema::default() };
+        for entry_type in &["attribute", "attibute"] {
+            let report = schema.validate(&manifest(entry_type)).await.unwrap();
+            assert!(report.is_valid(), "{}: {:?}", entry_type, report);
+        }
+        let report = schema.validate(&manifest("Function")).await.unwrap();
+        assert_eq!(report.diagnostics[0].suggestions, vec!["function"]);
+    }
+}
@@ -1,109 +1,142 @@
 use serde_json::{from_str, Value};
-use valico::json_schema;
-use crate::cache;
-use anyhow::{Result, Context, anyhow};
+use crate::cache::{CachePath, CacheStore};
+use crate::cache::fetch::{fetch, Refreshed};
+use super::{CompiledSchema, SourceMap, ValidationReport};
+use super::version::{SchemaVersion, select_version};
+use anyhow::{Result, Context};
 use tokio::prelude::*;
 use tokio::fs::File;
 use async_trait::async_trait;
-use colored::Colorize;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Gets the revision a schema was generated with by schema/compile.ts. Schemas from before revisions
+/// were added are treated as revision 0.
+fn revision(schema: &Value) -> u64 {
+    schema.get("revision").and_then(Value::as_u64).unwrap_or(0)
+}
 
 #[async_trait]
 pub trait Validator {
-    /// A function that returns a path to the validator.
-    fn path(&self) -> String;
+    /// A function that returns every version of the schema, oldest first. Files that don't declare
+    /// which version they target are validated against the first one.
+    fn versions(&self) -> &'static [SchemaVersion];
 
-    /// A function that returns the repo_url (a url to the schema.json) 
-    fn repo_url(&self) -> String;
+    /// A function that returns true if the validator should never touch the network or cache, and always
+    /// use the built-in schema.
+    fn offline(&self) -> bool;
 
-    /// A function that's run after any validation function is run. Use for any needed post-processing.
-    async fn after_validate(&self, val: &Value) -> Result<()>;
+    /// A function that returns how long a cached schema is used before checking for a newer one.
+    fn ttl(&self) -> Duration;
 
-    /// Attempts to load the schema from the file system, returning a copy of it.
-    /// Caches the schema for later use.
-    async fn load(&self) -> Result<Value> {
-        let file_path = cache::get_path(&self.path()).await?;
-        let mut file = File::open(file_path).await.context("Could not open schema.json")?;
-        let mut buffer = String::new();
-        file.read_to_string(&mut buffer).await?;
-        let schema: Value = from_str(&buffer).context("Failed to parse schema.min.json.")?;
+    /// A function that returns the store schemas are cached in.
+    fn store(&self) -> &dyn CacheStore;
+
+    /// A function that's run after schema validation, even if the schema found problems, so every problem is
+    /// reported together. Use for any checks the schema can't express, skipping any part of the value that
+    /// doesn't have the shape the checks need (the schema has already reported it). Problems should be
+    /// added to `report`; only return an `Err` if the checks couldn't be run.
+    async fn after_validate(&self, val: &Value, report: &mut ValidationReport) -> Result<()>;
+
+    /// A function that's run by `validate_file()` after `validate()`, given the directory the file is in.
+    /// Use for checks that need to look at files relative to the validated one. Does nothing by default.
+    async fn after_validate_file(&self, _val: &Value, _dir: &Path, _report: &mut ValidationReport) -> Result<()> {
+        Ok(())
+    }
+
+    /// Attempts to load a version of the schema from the store, returning a copy of it.
+    async fn load(&self, version: &SchemaVersion) -> Result<Value> {
+        let buffer = self.store().read_string(&CachePath::new(version.path)?).await?
+            .context(format!("{} is not cached.", version.path))?;
+        let schema: Value = from_str(&buffer).context(format!("Failed to parse {}.", version.path))?;
         Ok(schema)
     }
 
-    /// Downloads the schema from the GitHub source.
-    /// Fails if the schema isn't accessible. Doesn't continue if the file already exists.
-    async fn download(&self) -> Result<()> {
-        println!("Could not find {} in cache folder, downloading now...", &self.path());
-        let schema_path = cache::get_path(&self.path()).await.context("Could not get path to schema.")?;
-        if !schema_path.exists() {
-            let result = reqwest::get(&self.repo_url()).await
-                .context(format!("Could not get {} from GitHub source.", &self.path()))?
-                .text().await
-                .context(format!("Could not get {} from GitHub source.", &self.path()))?;
-            let mut out = cache::create_file(&self.path()).await.context(format!("Could not create {}", &self.path()))?;
-            out.write_all(&result.as_bytes()).await.context(format!("Could not write {}", &self.path()))?;
-        }
-        else {
-            return Err(anyhow!("Tried to download {} but it already existed.", &self.path()));
+    /// Makes sure the cached copy of a version of the schema is up to date, downloading it from the GitHub
+    /// source if it isn't cached. A cached copy is only checked against the server once it's older than
+    /// `ttl()`, unless `force` is true, and is only downloaded again if it's changed. Fails if the schema
+    /// isn't accessible.
+    async fn refresh(&self, version: &SchemaVersion, force: bool) -> Result<Refreshed> {
+        let refreshed = fetch(self.store(), &CachePath::new(version.path)?, version.url, self.ttl(), force).await
+            .context(format!("Could not get {} from GitHub source", version.path))?;
+        if refreshed == Refreshed::Updated {
+            eprintln!("Downloaded {} to cache folder.", version.path);
         }
-        println!("Downloaded {} to cache folder.", &self.path());
-
-        Ok(())
+        Ok(refreshed)
     }
 
-    /// Gets the cached version of the schema. Returns Some(schema) if it exists,
-    /// or returns None if it does not.
-    async fn get(&self) -> Result<Value> {
-        if let Ok(schema) = self.load().await {
-            Ok(schema)
+    /// Gets a version of the schema to validate against. Refreshes the cached copy, then uses it if its
+    /// revision is newer than the built-in schema's, and the built-in schema otherwise. Never touches the
+    /// network or cache if `offline()` is true. If the schema can't be refreshed, falls back to the cached
+    /// copy, or to the built-in schema if nothing is cached.
+    async fn get(&self, version: &SchemaVersion) -> Result<Value> {
+        let builtin: Value = from_str(version.builtin).context("Failed to parse the built-in schema.")?;
+        if self.offline() {
+            return Ok(builtin);
         }
-        else {
-            self.download().await.context("Could not download schema.")?;
-            Ok(self.load().await.context("Could not load schema from filesystem.")?)
+
+        let refreshed = self.refresh(version, false).await;
+        let cached = self.load(version).await.ok();
+        if let Err(err) = refreshed {
+            let fallback = if cached.is_some() { "cached" } else { "built-in" };
+            eprintln!("Could not refresh {}, using the {} copy: {:#}", version.path, fallback, err);
         }
+
+        match cached {
+            Some(schema) if revision(&schema) > revision(&builtin) => Ok(schema),
+            _ => Ok(builtin),
+        }
+    }
+
+    /// Compiles a version of the schema so values can be validated against it. Calls `get()` to get the
+    /// schema. Compiles the schema every time it's called; wrap the validator in a `CompiledValidator` to
+    /// only compile each version once.
+    async fn compile(&self, version: &SchemaVersion) -> Result<Arc<CompiledSchema>> {
+        let schema = self.get(version).await?;
+        Ok(Arc::new(CompiledSchema::compile(version, schema)?))
     }
 
     /// Validates a Serde Value against the Schema to be sure it fits all the required specs.
-    /// Returns None if there are no errors, and returns Some(errors) if the JSON isn't valid against the schema.
-    /// Calls `load_or_download()` to get the schema.
-    /// *Beware the gnarly Valico errors!*
-    async fn validate(&self, val: &Value) -> Result<()> {
-        let schema_json = self.get().await?;
-        let mut scope: json_schema::Scope = json_schema::Scope::new();
-
-        let schema = scope
-            .compile_and_return(schema_json, false).unwrap();
-        
-        let validate = schema.validate(&val);
-        if !validate.is_valid() {
-            let mut string: String = String::from("");
-            for error in validate.errors {
-                let err_body = &*error.get_title();
-                let err_path = &*error.get_path();
-                let error_complete = format!("  - {}: {}\n", err_body.yellow(), err_path);
-
-                string = format!("{} {}", string, error_complete);
-            }
-            Err(anyhow!("There were errors during validation. \n {}", string))
-            // Ok((Some(validate.errors), Some(())))
-        }
-        else {
-            self.after_validate(&val).await
+    /// Returns a report of every problem found; use `ValidationReport::is_valid` to check if the value is valid.
+    /// Only returns an `Err` if the schema couldn't be loaded. Picks the schema version from the value's
+    /// `schemaVersion` or `$schema` field, then calls `compile()` to get that version of the schema.
+    /// `after_validate()` is run after the schema, whether or not it found errors.
+    async fn validate(&self, val: &Value) -> Result<ValidationReport> {
+        let mut report = ValidationReport::new();
+        let version = match select_version(self.versions(), val, &mut report) {
+            Some(version) => version,
+            None => return Ok(report),
+        };
+
+        let schema = self.compile(version).await?;
+        for diagnostic in schema.validate(val) {
+            report.push(diagnostic);
         }
+
+        self.after_validate(val, &mut report).await?;
+        Ok(report)
     }
 
     /// Gets a JSON file, converting it to a Serde Value, then validates it against the schema.
-    /// Returns None if there are no errors, and returns Some(errors) if the JSON isn't valid against the schema.
-    /// Calls `load_or_download()` to get the schema.
-    /// *Beware the gnarly Valico errors!*
-    async fn validate_file(&self, file: &str) -> Result<()> {
+    /// Returns a report of every problem found. Fails if the file can't be read or isn't valid JSON.
+    /// Calls `validate()` to validate it, then `after_validate_file()` with the file's directory. Every
+    /// diagnostic in the report gets the location of its value in the file.
+    async fn validate_file(&self, file: &str) -> Result<ValidationReport> {
+        let dir = match Path::new(file).parent() {
+            Some(parent) if parent != Path::new("") => parent,
+            _ => Path::new("."),
+        };
         let mut file = File::open(&file).await.context("Could not open file to verify: ")?;
         let mut buffer = String::new();
         file.read_to_string(&mut buffer).await?;
         let to_validate: Value = from_str(&buffer).context("Could not open file to parse.")?;
-        self.validate(&to_validate).await
+        let mut report = self.validate(&to_validate).await?;
+        self.after_validate_file(&to_validate, dir, &mut report).await?;
+        let source_map = SourceMap::new(&buffer);
+        for diagnostic in &mut report.diagnostics {
+            diagnostic.location = source_map.locate(&diagnostic.pointer);
+        }
+        Ok(report)
     }
 }
-
-
-
-
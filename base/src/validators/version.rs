@@ -0,0 +1,151 @@
+use serde_json::Value;
+use super::{Diagnostic, ValidationReport};
+
+/// A version of a schema that Ibis knows about. Each version is cached in its own file, so files that
+/// target different versions can be validated side by side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchemaVersion {
+    /// The version number files use in `schemaVersion` to target this version.
+    pub version: u64,
+    /// The path of the cached copy of the schema, relative to the cache root.
+    pub path: &'static str,
+    /// The URL the schema is downloaded from. Files can also use it in `$schema` to target this version.
+    pub url: &'static str,
+    /// The copy of the schema compiled into Ibis.
+    pub builtin: &'static str,
+    /// Whether files targeting this version should get a warning.
+    pub deprecated: bool,
+}
+
+/// Lists the known version numbers for error messages, ex. `1, 2, 3`.
+fn known_versions(versions: &[SchemaVersion]) -> String {
+    versions.iter().map(|v| v.version.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+/// Picks the schema version a value targets from its `schemaVersion` and `$schema` fields. Values that
+/// don't declare a version (including anything that isn't an object) target the first version in `versions`.
+/// Returns None and adds an error to the report if the declared version is unknown, or if the two fields
+/// disagree. Adds a warning if the version is deprecated.
+pub fn select_version(versions: &'static [SchemaVersion], val: &Value, report: &mut ValidationReport) -> Option<&'static SchemaVersion> {
+    let mut failed = false;
+
+    let by_number = match val.get("schemaVersion") {
+        None => None,
+        Some(number) => {
+            let found = number.as_u64().and_then(|n| versions.iter().find(|v| v.version == n));
+            if found.is_none() {
+                failed = true;
+                report.push(Diagnostic::error(
+                    "/schemaVersion",
+                    "unknown-schema-version",
+                    &format!("Schema version {} is unknown. Known versions are: {}.", number, known_versions(versions)),
+                ));
+            }
+            found
+        }
+    };
+
+    let by_url = match val.get("$schema") {
+        None => None,
+        Some(url) => {
+            let found = url.as_str().and_then(|url| versions.iter().find(|v| v.url == url));
+            if found.is_none() {
+                failed = true;
+                report.push(Diagnostic::error(
+                    "/$schema",
+                    "unknown-schema-version",
+                    &format!("{} is not the URL of a known schema version. Known versions are: {}.", url, known_versions(versions)),
+                ));
+            }
+            found
+        }
+    };
+
+    if let (Some(number), Some(url)) = (by_number, by_url) {
+        if number.version != url.version {
+            failed = true;
+            report.push(Diagnostic::error(
+                "/schemaVersion",
+                "conflicting-schema-version",
+                &format!("`schemaVersion` targets version {}, but `$schema` targets version {}.", number.version, url.version),
+            ));
+        }
+    }
+
+    if failed {
+        return None;
+    }
+
+    let selected = by_number.or(by_url).or_else(|| versions.first())?;
+    if selected.deprecated {
+        report.push(Diagnostic::warning(
+            "",
+            "deprecated-schema-version",
+            &format!("Schema version {} is deprecated. Consider moving to version {}.", selected.version, versions.last()?.version),
+        ));
+    }
+    Some(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const VERSIONS: &[SchemaVersion] = &[
+        SchemaVersion { version: 1, path: "schema-v1.min.json", url: "https://example.com/v1.json", builtin: "{}", deprecated: true },
+        SchemaVersion { version: 2, path: "schema-v2.min.json", url: "https://example.com/v2.json", builtin: "{}", deprecated: false },
+    ];
+
+    /// Selects the version for a value, returning the version number and the rule and pointer of every
+    /// diagnostic added.
+    fn select(val: Value) -> (Option<u64>, Vec<(String, String)>) {
+        let mut report = ValidationReport::new();
+        let version = select_version(VERSIONS, &val, &mut report).map(|version| version.version);
+        (version, report.diagnostics.into_iter().map(|diagnostic| (diagnostic.rule, diagnostic.pointer)).collect())
+    }
+
+    #[test]
+    fn defaults_to_the_first_version() {
+        assert_eq!(select(json!({ "name": "example" })).0, Some(1));
+        assert_eq!(select(json!([])).0, Some(1));
+    }
+
+    #[test]
+    fn selects_declared_versions() {
+        assert_eq!(select(json!({ "schemaVersion": 2 })), (Some(2), vec![]));
+        assert_eq!(select(json!({ "$schema": "https://example.com/v2.json" })), (Some(2), vec![]));
+        assert_eq!(select(json!({ "schemaVersion": 2, "$schema": "https://example.com/v2.json" })), (Some(2), vec![]));
+    }
+
+    #[test]
+    fn refuses_unknown_versions() {
+        let unknown = vec![(String::from("unknown-schema-version"), String::from("/schemaVersion"))];
+        assert_eq!(select(json!({ "schemaVersion": 3 })), (None, unknown.clone()));
+        assert_eq!(select(json!({ "schemaVersion": "2" })), (None, unknown));
+        assert_eq!(
+            select(json!({ "$schema": "https://example.com/v3.json" })),
+            (None, vec![(String::from("unknown-schema-version"), String::from("/$schema"))]),
+        );
+    }
+
+    #[test]
+    fn refuses_conflicting_versions() {
+        assert_eq!(
+            select(json!({ "schemaVersion": 2, "$schema": "https://example.com/v1.json" })),
+            (None, vec![(String::from("conflicting-schema-version"), String::from("/schemaVersion"))]),
+        );
+    }
+
+    #[test]
+    fn warns_about_deprecated_versions() {
+        let mut report = ValidationReport::new();
+        assert_eq!(select_version(VERSIONS, &json!({ "schemaVersion": 1 }), &mut report).map(|version| version.version), Some(1));
+        assert_eq!(report.diagnostics, vec![Diagnostic::warning(
+            "",
+            "deprecated-schema-version",
+            "Schema version 1 is deprecated. Consider moving to version 2.",
+        )]);
+        assert!(report.is_valid());
+    }
+}
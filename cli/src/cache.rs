@@ -0,0 +1,28 @@
+mod refresh;
+mod info;
+mod prune;
+mod clear;
+
+use clap::{App, ArgMatches};
+use anyhow::Result;
+
+// The Clap subcommand for the cache module.
+pub fn subcommand<'a>() -> App<'a> {
+    App::new("cache")
+        .about("Commands for managing the files Ibis downloads and caches.")
+        .version("0.1.0")
+        .subcommand(info::subcommand())
+        .subcommand(refresh::subcommand())
+        .subcommand(prune::subcommand())
+        .subcommand(clear::subcommand())
+}
+
+pub async fn run(app: &ArgMatches) -> Result<()> {
+    if let Some(cache_command) = app.subcommand_matches("cache") {
+        info::run(cache_command).await?;
+        refresh::run(cache_command).await?;
+        prune::run(cache_command).await?;
+        clear::run(cache_command).await?;
+    }
+    Ok(())
+}
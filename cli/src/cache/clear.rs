@@ -0,0 +1,21 @@
+use clap::{App, ArgMatches};
+use colored::Colorize;
+use base::cache::FsStore;
+use base::cache::maintenance::clear;
+use anyhow::{Result, Context};
+
+// The Clap subcommand for the clear module.
+pub fn subcommand<'a>() -> App<'a> {
+    App::new("clear")
+        .about("Removes everything in the cache except config.json, including installed packages.")
+        .version("0.1.0")
+}
+
+/// Runs the clear subcommand.
+pub async fn run(app: &ArgMatches) -> Result<()> {
+    if app.subcommand_matches("clear").is_some() {
+        let removed = clear(&FsStore::new()).await.context("Could not clear the cache.")?;
+        println!("{}", format!("Removed {} file(s) from the cache.", removed).green().bold());
+    }
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use clap::{App, ArgMatches};
+use colored::Colorize;
+use base::cache::FsStore;
+use base::cache::maintenance::{usage, KindUsage};
+use anyhow::{Result, Context};
+
+// The Clap subcommand for the info module.
+pub fn subcommand<'a>() -> App<'a> {
+    App::new("info")
+        .about("Shows where the cache is and how much space it takes up.")
+        .version("0.1.0")
+}
+
+/// Formats a number of bytes for people, ex. `1.2 MB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+fn format_usage(usage: &KindUsage) -> String {
+    format!("{} in {} file(s)", format_size(usage.bytes), usage.files)
+}
+
+/// Runs the info subcommand.
+pub async fn run(app: &ArgMatches) -> Result<()> {
+    if app.subcommand_matches("info").is_some() {
+        let store = FsStore::new();
+        let root = store.root().await.context("Could not get cache path.")?;
+        let usage = usage(&store).await.context("Could not read the cache.")?;
+
+        println!("{} {}", "Cache directory:".blue().bold(), root.display());
+        println!("{} {}", "Total:".blue().bold(), format_usage(&usage.total()));
+        for (kind, kind_usage) in &usage.by_kind {
+            println!("  - {}: {}", kind.name().bold(), format_usage(kind_usage));
+        }
+        if !usage.package_versions.is_empty() {
+            println!("{} {}", "Package versions:".blue().bold(), usage.package_versions.len());
+        }
+    }
+    Ok(())
+}
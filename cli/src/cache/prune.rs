@@ -0,0 +1,38 @@
+use clap::{App, Arg, ArgMatches};
+use colored::Colorize;
+use base::cache::FsStore;
+use base::cache::maintenance::{prunable, prune};
+use anyhow::{Result, Context};
+
+// The Clap subcommand for the prune module.
+pub fn subcommand<'a>() -> App<'a> {
+    App::new("prune")
+        .about("Removes temporary files, unused schemas, package versions that aren't recorded as installed, and the git repositories of packages that aren't installed.")
+        .version("0.1.0")
+        .arg(
+            Arg::with_name("dry-run")
+                .long("dry-run")
+                .about("Lists what would be removed without removing anything."),
+        )
+}
+
+/// Runs the prune subcommand.
+pub async fn run(app: &ArgMatches) -> Result<()> {
+    if let Some(prune_command) = app.subcommand_matches("prune") {
+        let store = FsStore::new();
+        let dry_run = prune_command.is_present("dry-run");
+        let paths = if dry_run { prunable(&store).await } else { prune(&store).await }
+            .context("Could not prune the cache.")?;
+
+        if paths.is_empty() {
+            println!("{}", "Nothing to prune.".green().bold());
+            return Ok(());
+        }
+        let verb = if dry_run { "Would remove" } else { "Removed" };
+        println!("{}", format!("{} {} item(s):", verb, paths.len()).green().bold());
+        for path in &paths {
+            println!("  - {}", path);
+        }
+    }
+    Ok(())
+}
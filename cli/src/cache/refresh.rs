@@ -0,0 +1,49 @@
+use clap::{App, ArgMatches};
+use colored::Colorize;
+use base::cache::FsStore;
+use base::cache::fetch::Refreshed;
+use base::config::Config;
+use base::validators::{PackageSchema, RepoSchema, SchemaVersion, Validator, PACKAGE_SCHEMA_VERSIONS, REPO_SCHEMA_VERSIONS};
+use anyhow::{Result, Context, anyhow};
+use std::sync::Arc;
+
+// The Clap subcommand for the refresh module.
+pub fn subcommand<'a>() -> App<'a> {
+    App::new("refresh")
+        .about("Checks every cached schema for a newer version right away, ignoring the cache TTL.")
+        .version("0.1.0")
+}
+
+/// Refreshes every version of a validator's schema, printing what happened to each one.
+/// Returns the number of versions that couldn't be refreshed.
+async fn refresh_all(validator: &(impl Validator + Sync), versions: &[SchemaVersion]) -> usize {
+    let mut failed = 0;
+    for version in versions {
+        match validator.refresh(version, true).await {
+            Ok(Refreshed::Updated) => println!("{}", format!("Updated {}.", version.path).green().bold()),
+            Ok(_) => println!("{}", format!("{} is up to date.", version.path).green().bold()),
+            Err(err) => {
+                eprintln!("{}{:#}", "Could not refresh: ".red().bold(), err);
+                failed += 1;
+            }
+        }
+    }
+    failed
+}
+
+/// Runs the refresh subcommand. Fails if any schema couldn't be refreshed.
+pub async fn run(app: &ArgMatches) -> Result<()> {
+    if app.subcommand_matches("refresh").is_some() {
+        let store = Arc::new(FsStore::new());
+        let config = Config::load(&*store).await.context("Could not load config.")?;
+        let package = PackageSchema { cache: config.cache.clone(), store: store.clone(), ..PackageSchema::default() };
+        let repo = RepoSchema { cache: config.cache, store, ..RepoSchema::default() };
+
+        println!("{}", "Refreshing cached schemas...".blue().bold());
+        let failed = refresh_all(&package, PACKAGE_SCHEMA_VERSIONS).await + refresh_all(&repo, REPO_SCHEMA_VERSIONS).await;
+        if failed > 0 {
+            return Err(anyhow!("{} schema(s) could not be refreshed.", failed));
+        }
+    }
+    Ok(())
+}
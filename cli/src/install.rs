@@ -0,0 +1,61 @@
+use clap::{App, Arg, ArgMatches};
+use colored::Colorize;
+use std::sync::Arc;
+use base::cache::FsStore;
+use base::config::Config;
+use base::install::{install, InstallStatus};
+use base::registry::resolve_registries;
+use base::resolve::{ResolveError, VersionSpec};
+use base::validators::PackageSchema;
+use anyhow::{Result, Context};
+
+// The Clap subcommand for the install module.
+pub fn subcommand<'a>() -> App<'a> {
+    App::new("install")
+        .about("Installs a version of a package from its git repository.")
+        .version("0.1.0")
+        .arg(
+            Arg::with_name("name")
+                .index(1)
+                .required(true)
+                .about("The package to install, ex. `rust`, `rust@^1.70`, `rust@1.70.0`, or `rust@latest`. Installs the latest version if no version is given."),
+        )
+}
+
+/// Runs the install subcommand.
+pub async fn run(app: &ArgMatches) -> Result<()> {
+    if let Some(install_command) = app.subcommand_matches("install") {
+        let (name, spec) = VersionSpec::split(install_command.value_of("name").unwrap_or_default());
+        let store = Arc::new(FsStore::new());
+        let config = Config::load(&*store).await.context("Could not load config.")?;
+        let registries = resolve_registries(&config.registries, install_command.value_of("registry"));
+        let schema = PackageSchema { offline: false, lint: config.lint, cache: config.cache, store: store.clone() };
+
+        println!("{}", format!("Installing {}...", name).blue().bold());
+        let result = match install(&*store, schema, &registries, name, &spec).await {
+            Ok(result) => result,
+            Err(err) => {
+                if let Some(ResolveError::NoMatch { candidates, .. }) = err.downcast_ref::<ResolveError>() {
+                    eprintln!("{}", "Versions considered:".yellow().bold());
+                    for candidate in candidates {
+                        eprintln!("  - {}", candidate);
+                    }
+                }
+                return Err(err.context(format!("Could not install {}.", name)));
+            }
+        };
+
+        let package = &result.package;
+        let commit = match &package.revision {
+            base::installed::Revision::Git { commit } => &commit[..commit.len().min(7)],
+            base::installed::Revision::Archive { sha256 } => &sha256[..sha256.len().min(7)],
+        };
+        let line = match result.status {
+            InstallStatus::Installed => format!("Installed {}@{} ({}) from {}.", package.name, package.version, commit, result.registry),
+            InstallStatus::Replaced => format!("Reinstalled {}@{} ({}) from {}.", package.name, package.version, commit, result.registry),
+            InstallStatus::AlreadyInstalled => format!("{}@{} ({}) is already installed.", package.name, package.version, commit),
+        };
+        println!("{}", line.green().bold());
+    }
+    Ok(())
+}
@@ -1,19 +1,56 @@
 mod package;
+mod cache;
+mod install;
+mod registry;
+mod sync;
 
-use clap::{App, AppSettings};
-use tokio::join;
+use clap::{App, AppSettings, Arg};
+use colored::Colorize;
+use std::path::PathBuf;
 
-/// Creates and runs the main CLI app.
+/// Creates and runs the main CLI app. Exits with a nonzero status if the command failed.
 #[tokio::main]
-async fn main() -> () {
+async fn main() {
     let app = App::new("Ibis")
         .version("0.1.0")
         .author("Sam Wight <samuelwight@gmail.com>")
         .about("Ibis is a documentation package manager, viewer, and search engine.")
+        .arg(
+            Arg::with_name("cache-dir")
+                .long("cache-dir")
+                .global(true)
+                .takes_value(true)
+                .about("The directory Ibis keeps its cache, config, and packages in. Overrides IBIS_HOME and the XDG directories (default ~/.ibis)."),
+        )
+        .arg(
+            Arg::with_name("registry")
+                .long("registry")
+                .global(true)
+                .takes_value(true)
+                .about("Only look packages up in this registry: the name of a registry in config.json, or the URL or path of a registry."),
+        )
         .subcommand(package::subcommand())
+        .subcommand(cache::subcommand())
+        .subcommand(install::subcommand())
+        .subcommand(registry::subcommand())
+        .subcommand(sync::subcommand())
         .setting(AppSettings::ArgRequiredElseHelp)
         .get_matches();
 
-    let validate_fut = package::run(&app);
-    join!(validate_fut);
+    if let Some(cache_dir) = app.value_of("cache-dir") {
+        // Only fails if it's already set, which can't happen this early.
+        base::cache::set_cache_dir(PathBuf::from(cache_dir)).ok();
+    }
+
+    let result = async {
+        package::run(&app).await?;
+        cache::run(&app).await?;
+        install::run(&app).await?;
+        registry::run(&app).await?;
+        sync::run(&app).await
+    }.await;
+    if let Err(err) = result {
+        eprintln!("{}{:?}", "Error: ".red().bold(), err);
+        std::process::exit(1);
+    }
 }
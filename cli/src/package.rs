@@ -3,6 +3,7 @@ mod get_url;
 
 use clap::{App, ArgMatches};
 use tokio::join;
+use anyhow::Result;
 
 // The Clap subcommand for the validate module.
 pub fn subcommand<'a>() -> App<'a> {
@@ -13,10 +14,13 @@ pub fn subcommand<'a>() -> App<'a> {
         .subcommand(get_url::subcommand())
 }
 
-pub async fn run(app: &ArgMatches) {
-    if let Some(ref verify_command) = app.subcommand_matches("package") {
-        let v = validate::run(&verify_command);
-        let g = get_url::run(&verify_command);
-        join!(v, g);
+pub async fn run(app: &ArgMatches) -> Result<()> {
+    if let Some(verify_command) = app.subcommand_matches("package") {
+        let v = validate::run(verify_command);
+        let g = get_url::run(verify_command);
+        let (validated, got) = join!(v, g);
+        validated?;
+        got?;
     }
+    Ok(())
 }
\ No newline at end of file
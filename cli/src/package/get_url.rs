@@ -1,6 +1,9 @@
 use clap::{App, Arg, ArgMatches};
 use colored::Colorize;
+use base::cache::FsStore;
+use base::config::Config;
 use base::packages::get_package_url;
+use base::registry::resolve_registries;
 use anyhow::{Result, Context};
 
 // The Clap subcommand for the validate module.
@@ -15,20 +18,26 @@ pub fn subcommand<'a>() -> App<'a> {
         )
 }
 
-pub async fn get_url(name: &str) {
+/// Looks a package up in the configured registries (or only `registry`, if given) and prints its
+/// repo URL along with the registry that had it.
+pub async fn get_url(name: &str, registry: Option<&str>) -> Result<()> {
+    let config = Config::load(&FsStore::new()).await.context("Could not load config.")?;
+    let registries = resolve_registries(&config.registries, registry);
+
     println!("{}{}{}", "Getting the URL of ".blue().bold(), name.blue().bold(), "...".blue().bold());
-    match get_package_url(&String::from(name)).await {
-        Ok(result) => println!("{}{}", "Package repo URL: ".green().bold(), result),
-        Err(e) => println!("{:?}", e)
-    }
+    let lookup = get_package_url(&registries, name).await?;
+    println!("{}{}", "Package repo URL: ".green().bold(), lookup.url);
+    println!("{}{}", "Found in registry: ".green().bold(), lookup.registry);
+    Ok(())
 }
 
-pub async fn run(app: &ArgMatches) {
-    if let Some(ref get_url_command) = app.subcommand_matches("get-url") {
+pub async fn run(app: &ArgMatches) -> Result<()> {
+    if let Some(get_url_command) = app.subcommand_matches("get-url") {
         let file = get_url_command.value_of("name");
         match file {
-            Some(result) => get_url(result).await,
+            Some(result) => get_url(result, get_url_command.value_of("registry")).await?,
             None => println!("{}", "Please enter a package name to get the URL of.".red().bold())
         }
     }
+    Ok(())
 }
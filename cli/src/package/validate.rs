@@ -1,37 +1,169 @@
+mod output;
+mod discover;
+mod fix;
+
 use clap::{App, Arg, ArgMatches};
 use colored::Colorize;
-use base::validators::{PackageSchema, Validator};
-use anyhow::{Result, Context};
+use base::cache::FsStore;
+use base::config::Config;
+use base::validators::{CompiledValidator, PackageSchema, Validator};
+use base::validators::lint::LINT_RULES;
+use anyhow::{Result, Context, anyhow};
+use futures::stream::{self, StreamExt};
+use output::{FileResult, Format};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Validates a file against the schema, printing the report in the given format.
+/// Fails if the file couldn't be validated or has any errors.
+async fn validate_schema(file: &str, format: Format, ps: PackageSchema) -> Result<()> {
+    let report = ps.validate_file(file).await;
+    let report = match report {
+        Ok(report) => report,
+        // Machine-readable formats still get their usual structure, with the file marked as failed.
+        Err(err) if format != Format::Text => {
+            output::print_results(format, &[FileResult { file: String::from(file), report: Err(err) }]);
+            return Err(anyhow!("{} could not be validated.", file));
+        }
+        Err(err) => return Err(err.context("Could not validate file against schema.")),
+    };
+
+    output::print_report(format, file, &report);
 
-/// Validates a file against the schema.
-async fn validate_schema(file: &str) -> Result<()> {
-    let ps = PackageSchema {};
-    ps.validate_file(&file)
+    if report.is_valid() { Ok(()) } else { Err(anyhow!("{} is not valid.", file)) }
+}
+
+/// Validates many files in parallel, running at most `jobs` validations at once. The schema is only
+/// compiled once for all of the files. Prints the results for every file and a summary once they're all done.
+/// Fails if any file is invalid or couldn't be validated.
+async fn validate_many(files: Vec<PathBuf>, format: Format, ps: PackageSchema, jobs: usize) -> Result<()> {
+    let validator = Arc::new(CompiledValidator::new(ps));
+    let mut results = stream::iter(files.into_iter().enumerate())
+        .map(|(i, path)| {
+            let validator = validator.clone();
+            tokio::spawn(async move {
+                let file = path.to_string_lossy().into_owned();
+                let report = validator.validate_file(&file).await;
+                (i, FileResult { file, report })
+            })
+        })
+        .buffer_unordered(jobs)
+        .collect::<Vec<_>>()
         .await
-        .context("Could not validate file against schema.")?;
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .context("A validation task failed.")?;
+    results.sort_by_key(|(i, _)| *i);
+    let results: Vec<FileResult> = results.into_iter().map(|(_, result)| result).collect();
 
-    println!("{}", "Schema validated with no errors!".green().bold());
+    output::print_results(format, &results);
 
-    Ok(())
+    let invalid = results.iter().filter(|result| !result.is_valid()).count();
+    if invalid == 0 { Ok(()) } else { Err(anyhow!("{} of {} file(s) are not valid.", invalid, results.len())) }
 }
 
 // The Clap subcommand for the validate module.
 pub fn subcommand<'a>() -> App<'a> {
     App::new("validate")
-        .about("Validates a ibis.json file. Downloads the schema.json file from GitHub if it's not found.")
+        .about("Validates ibis.json files. Uses the built-in schema unless a newer one is cached or can be downloaded.")
         .version("0.1.0")
         .arg(
             Arg::with_name("file")
                 .index(1)
-                .about("The path to the ibis.json file to verify (default ./ibis.json)."),
+                .multiple(true)
+                .about("The ibis.json files to verify (default ./ibis.json). Directories are searched for ibis.json files, and glob patterns are expanded."),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&Format::NAMES)
+                .default_value("text")
+                .about("The format to print the results in."),
+        )
+        .arg(
+            Arg::with_name("offline")
+                .long("offline")
+                .about("Only use the schema built into Ibis. Never downloads or reads a cached schema."),
+        )
+        .arg(
+            Arg::with_name("allow")
+                .long("allow")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .possible_values(&LINT_RULES.iter().map(|rule| rule.id).collect::<Vec<_>>())
+                .about("Turns off a lint rule, in addition to the ones turned off in config.json. Can be used more than once."),
+        )
+        .arg(
+            Arg::with_name("fix")
+                .long("fix")
+                .about("Repairs common problems in place before validating: drops unknown and duplicate page IDs, normalizes path separators, and sorts the page table."),
+        )
+        .arg(
+            Arg::with_name("jobs")
+                .long("jobs")
+                .short('j')
+                .takes_value(true)
+                .default_value("8")
+                .about("The number of files to validate at once when validating more than one file."),
         )
 }
 
-pub async fn run(app: &ArgMatches) {
-    if let Some(ref verify_command) = app.subcommand_matches("validate") {
-        let file = verify_command.value_of("file").unwrap_or("ibis.json");
-        let line_to_print = format!("Validating the config of {}...", file);
-        println!("{}", line_to_print.blue().bold());
-        validate_schema(&file).await.unwrap_or_else(|err| println!("{}{:?}", "Error while validating: ".red().bold(), err));
+/// Runs the validate subcommand. Returns an error if any file is invalid so the CLI can exit
+/// with a nonzero status.
+pub async fn run(app: &ArgMatches) -> Result<()> {
+    if let Some(verify_command) = app.subcommand_matches("validate") {
+        let inputs: Vec<&str> = verify_command.values_of("file").map(|files| files.collect()).unwrap_or_else(|| vec!["ibis.json"]);
+        let format = Format::from_name(verify_command.value_of("format").unwrap_or("text"));
+        let offline = verify_command.is_present("offline");
+        let store = Arc::new(FsStore::new());
+        let config = Config::load(&*store).await.context("Could not load config.")?;
+        let mut lint = config.lint;
+        for id in lint.unknown_rules() {
+            let known: Vec<&str> = LINT_RULES.iter().map(|rule| rule.id).collect();
+            eprintln!("{}", format!("Ignoring unknown lint rule `{}` in lint.disabled. Known rules are: {}.", id, known.join(", ")).yellow());
+        }
+        if let Some(allowed) = verify_command.values_of("allow") {
+            lint.disabled.extend(allowed.map(String::from));
+        }
+        let ps = PackageSchema { offline, lint, cache: config.cache, store };
+        let jobs: usize = verify_command.value_of("jobs").unwrap_or("8").parse()
+            .ok().filter(|jobs| *jobs > 0)
+            .context("--jobs must be a number greater than 0.")?;
+
+        let single = match inputs[..] {
+            [file] if !discover::is_glob(file) && !Path::new(file).is_dir() => Some(file),
+            _ => None,
+        };
+        let files = match single {
+            Some(file) => vec![PathBuf::from(file)],
+            None => discover::find_manifests(&inputs)?,
+        };
+        if files.is_empty() {
+            return Err(anyhow!("Could not find any ibis.json files to validate."));
+        }
+
+        if verify_command.is_present("fix") {
+            for file in &files {
+                if let Err(err) = fix::fix_file(file, format).await {
+                    eprintln!("{}{:#}", "Could not fix file: ".red().bold(), err);
+                }
+            }
+        }
+
+        if let Some(file) = single {
+            if format == Format::Text {
+                let line_to_print = format!("Validating the config of {}...", file);
+                println!("{}", line_to_print.blue().bold());
+            }
+            return validate_schema(file, format, ps).await;
+        }
+
+        if format == Format::Text {
+            println!("{}", format!("Validating the configs of {}...", inputs.join(", ")).blue().bold());
+        }
+        validate_many(files, format, ps, jobs).await?;
     }
+    Ok(())
 }
@@ -0,0 +1,121 @@
+use anyhow::{Result, Context, anyhow};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The name of the files searched for in directories.
+const MANIFEST_NAME: &str = "ibis.json";
+
+/// Returns true if the input contains any glob characters and should be expanded as a pattern.
+pub fn is_glob(input: &str) -> bool {
+    input.contains(['*', '?', '['])
+}
+
+/// Recursively searches a directory for ibis.json files. Skips hidden directories and `node_modules`.
+fn search_dir(dir: &Path, found: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries = fs::read_dir(dir)
+        .context(format!("Could not read directory {}.", dir.display()))?
+        .collect::<Result<Vec<_>, _>>()
+        .context(format!("Could not read directory {}.", dir.display()))?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "node_modules" {
+                search_dir(&path, found)?;
+            }
+        }
+        else if name == MANIFEST_NAME {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Adds a path to the list of found files. Directories are searched for ibis.json files and anything else is
+/// treated as a file to validate.
+fn add_path(path: PathBuf, found: &mut Vec<PathBuf>) -> Result<()> {
+    if path.is_dir() {
+        search_dir(&path, found)
+    }
+    else {
+        found.push(path);
+        Ok(())
+    }
+}
+
+/// Expands the inputs given to `package validate` into the list of files to validate, in order and without
+/// duplicates. Glob patterns are expanded, directories are searched recursively for ibis.json files, and
+/// anything else is treated as a path to a file.
+pub fn find_manifests(inputs: &[&str]) -> Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    for input in inputs {
+        if is_glob(input) {
+            let matches = glob::glob(input).context(format!("{} is not a valid glob pattern.", input))?;
+            let before = found.len();
+            for path in matches {
+                add_path(path.context(format!("Could not read a match of {}.", input))?, &mut found)?;
+            }
+            if found.len() == before {
+                return Err(anyhow!("No files matched {}.", input));
+            }
+        }
+        else {
+            add_path(PathBuf::from(input), &mut found)?;
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    found.retain(|path| seen.insert(path.clone()));
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Creates the given files (with any missing directories) in a new temporary directory.
+    fn tree(files: &[&str]) -> TempDir {
+        let root = TempDir::new().unwrap();
+        for file in files {
+            let path = root.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "{}").unwrap();
+        }
+        root
+    }
+
+    /// Finds the manifests for inputs relative to `root`, returning them relative to `root` too.
+    fn find(root: &TempDir, inputs: &[&str]) -> Result<Vec<String>> {
+        let inputs: Vec<String> = inputs.iter().map(|input| root.path().join(input).to_string_lossy().into_owned()).collect();
+        let inputs: Vec<&str> = inputs.iter().map(String::as_str).collect();
+        Ok(find_manifests(&inputs)?.iter()
+            .map(|path| path.strip_prefix(root.path()).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect())
+    }
+
+    #[test]
+    fn searches_nested_directories() {
+        let root = tree(&["ibis.json", "b/ibis.json", "a/deep/ibis.json", "a/other.json", ".git/ibis.json", "node_modules/x/ibis.json"]);
+        assert_eq!(find(&root, &[""]).unwrap(), vec!["a/deep/ibis.json", "b/ibis.json", "ibis.json"]);
+        assert_eq!(find(&root, &["a", "a/other.json"]).unwrap(), vec!["a/deep/ibis.json", "a/other.json"]);
+    }
+
+    #[test]
+    fn expands_globs() {
+        let root = tree(&["a/ibis.json", "b/ibis.json", "b/c/ibis.json", "d/other.json"]);
+        assert_eq!(find(&root, &["*/ibis.json"]).unwrap(), vec!["a/ibis.json", "b/ibis.json"]);
+        assert_eq!(find(&root, &["[bd]", "b/ibis.json"]).unwrap(), vec!["b/c/ibis.json", "b/ibis.json"]);
+        assert!(find(&root, &["*/missing.json"]).unwrap_err().to_string().starts_with("No files matched"));
+    }
+
+    #[test]
+    fn recognizes_globs() {
+        assert!(is_glob("packages/*/ibis.json"));
+        assert!(is_glob("ibis.jso?"));
+        assert!(!is_glob("packages/ibis.json"));
+    }
+}
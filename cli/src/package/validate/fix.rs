@@ -0,0 +1,52 @@
+use colored::Colorize;
+use base::cache::write_atomic;
+use base::validators::fix::fix_source;
+use anyhow::{Result, Context};
+use similar::TextDiff;
+use std::path::Path;
+use tokio::fs;
+use super::output::Format;
+
+/// Colors a unified diff line by line: additions green, removals red, and hunk headers cyan.
+fn color_diff(diff: &str) -> String {
+    diff.lines()
+        .map(|line| {
+            if line.starts_with("+++") || line.starts_with("---") { line.bold().to_string() }
+            else if line.starts_with('+') { line.green().to_string() }
+            else if line.starts_with('-') { line.red().to_string() }
+            else if line.starts_with("@@") { line.cyan().to_string() }
+            else { String::from(line) }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Makes every safe repair to a file and writes it back in place, then prints what was changed and a diff.
+/// Output goes to stdout for the text format and to stderr otherwise, so machine-readable output stays valid.
+pub async fn fix_file(file: &Path, format: Format) -> Result<()> {
+    let name = file.to_string_lossy();
+    let original = fs::read_to_string(file).await.context(format!("Could not read {}.", name))?;
+    let outcome = fix_source(&original)?;
+
+    let mut lines = Vec::new();
+    if outcome.fixes.is_empty() {
+        lines.push(format!("{}", format!("Nothing to fix in {}.", name).green().bold()));
+    }
+    else {
+        write_atomic(file, outcome.source.as_bytes()).await.context(format!("Could not write {}.", name))?;
+        lines.push(format!("{}", format!("Fixed {} problem(s) in {}:", outcome.fixes.len(), name).green().bold()));
+        for fix in &outcome.fixes {
+            lines.push(format!("  - {}: {}", fix.pointer.bold(), fix.message));
+        }
+        let diff = TextDiff::from_lines(&original, &outcome.source)
+            .unified_diff()
+            .context_radius(3)
+            .header(&format!("a/{}", name), &format!("b/{}", name))
+            .to_string();
+        lines.push(color_diff(&diff));
+    }
+
+    let output = lines.join("\n");
+    if format == Format::Text { println!("{}", output) } else { eprintln!("{}", output) }
+    Ok(())
+}
@@ -0,0 +1,378 @@
+use colored::Colorize;
+use base::validators::{Diagnostic, Location, Severity, ValidationReport};
+use anyhow::Error;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+
+/// The formats `package validate` can print a report in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Sarif,
+}
+
+impl Format {
+    pub const NAMES: [&'static str; 3] = ["text", "json", "sarif"];
+
+    /// Gets a format from its name on the command line. Defaults to `Text` for unknown names.
+    pub fn from_name(name: &str) -> Format {
+        match name {
+            "json" => Format::Json,
+            "sarif" => Format::Sarif,
+            _ => Format::Text,
+        }
+    }
+}
+
+/// The result of validating a single file. `report` is an `Err` if the file couldn't be validated at all,
+/// ex. if it couldn't be read or isn't JSON.
+pub struct FileResult {
+    pub file: String,
+    pub report: Result<ValidationReport, Error>,
+}
+
+impl FileResult {
+    /// Returns true if the file was validated and has no errors.
+    pub fn is_valid(&self) -> bool {
+        self.report.as_ref().is_ok_and(ValidationReport::is_valid)
+    }
+}
+
+/// Prints the line of the file a diagnostic's value starts on, rustc-style, with the value underlined.
+/// Values that span more than one line are underlined to the end of their first line.
+fn print_snippet(file: &str, source: &str, location: &Location, severity: Severity) {
+    let line = match source.lines().nth(location.line - 1) {
+        Some(line) => line.trim_end_matches('\r'),
+        None => return,
+    };
+    let line_number = location.line.to_string();
+    let gutter = " ".repeat(line_number.len());
+    let end_column = if location.end_line == location.line { location.end_column } else { line.chars().count() + 1 };
+    // Keep tabs in the padding so the underline lines up with the value however wide tabs are shown.
+    let padding: String = line.chars()
+        .take(location.column - 1)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let underline = "^".repeat(end_column.saturating_sub(location.column).max(1));
+    let underline = match severity {
+        Severity::Error => underline.red().bold(),
+        Severity::Warning => underline.yellow().bold(),
+    };
+
+    println!("    {}{} {}:{}:{}", gutter, "-->".blue().bold(), file, location.line, location.column);
+    println!("    {} {}", gutter, "|".blue().bold());
+    println!("    {} {} {}", line_number.blue().bold(), "|".blue().bold(), line);
+    println!("    {} {} {}{}", gutter, "|".blue().bold(), padding, underline);
+}
+
+/// Prints a single diagnostic as a colored line, ex. `  - error[enum] /pages/foo/entryType: ...`. If the
+/// diagnostic has a location and the file's source is given, the offending value is shown under it.
+fn print_diagnostic(file: &str, source: Option<&str>, diagnostic: &Diagnostic) {
+    let label = match diagnostic.severity {
+        Severity::Error => "error".red().bold(),
+        Severity::Warning => "warning".yellow().bold(),
+    };
+    let pointer = if diagnostic.pointer.is_empty() { "/" } else { &diagnostic.pointer };
+    println!("  - {}[{}] {}: {}", label, diagnostic.rule, pointer.bold(), diagnostic.message);
+    if let (Some(source), Some(location)) = (source, &diagnostic.location) {
+        print_snippet(file, source, location, diagnostic.severity);
+    }
+    if !diagnostic.suggestions.is_empty() {
+        println!("    {} did you mean {}?", "help:".cyan().bold(), suggestion_list(&diagnostic.suggestions));
+    }
+}
+
+/// Lists suggestions for a message, ex. `` `class` or `function` ``.
+fn suggestion_list(suggestions: &[String]) -> String {
+    suggestions.iter().map(|suggestion| format!("`{}`", suggestion)).collect::<Vec<_>>().join(" or ")
+}
+
+/// Prints every diagnostic in a report, reading the file again so the offending values can be shown.
+fn print_diagnostics(file: &str, report: &ValidationReport) {
+    let source = std::fs::read_to_string(file).ok();
+    for diagnostic in &report.diagnostics {
+        print_diagnostic(file, source.as_deref(), diagnostic);
+    }
+}
+
+/// Prints every diagnostic in the report as colored text, followed by a summary line.
+fn print_text(file: &str, report: &ValidationReport) {
+    print_diagnostics(file, report);
+
+    let errors = report.errors().count();
+    let warnings = report.warnings().count();
+    if errors == 0 && warnings == 0 {
+        println!("{}", "Schema validated with no errors!".green().bold());
+    }
+    else if errors == 0 {
+        println!("{}", format!("Schema validated with {} warning(s).", warnings).yellow().bold());
+    }
+    else {
+        println!("{}", format!("Found {} error(s) and {} warning(s).", errors, warnings).red().bold());
+    }
+}
+
+/// Prints the results of validating many files as colored text: each file that has diagnostics or couldn't
+/// be validated, followed by a summary of all the files.
+fn print_text_many(results: &[FileResult]) {
+    for result in results {
+        match &result.report {
+            Ok(report) if report.diagnostics.is_empty() => {}
+            Ok(report) => {
+                println!("{}", result.file.bold());
+                print_diagnostics(&result.file, report);
+            }
+            Err(err) => {
+                println!("{}", result.file.bold());
+                println!("  - {} {:#}", "failed".red().bold(), err);
+            }
+        }
+    }
+
+    let valid = results.iter().filter(|r| r.is_valid()).count();
+    let failed = results.iter().filter(|r| r.report.is_err()).count();
+    let invalid = results.len() - valid - failed;
+    let summary = format!("Validated {} file(s): {} valid, {} invalid, {} could not be validated.", results.len(), valid, invalid, failed);
+    if valid == results.len() {
+        println!("{}", summary.green().bold());
+    }
+    else {
+        println!("{}", summary.red().bold());
+    }
+}
+
+/// Builds the JSON entry for one file in the output of `to_json()`.
+fn file_json(file: &str, report: &ValidationReport) -> Value {
+    json!({
+        "file": file,
+        "valid": report.is_valid(),
+        "errors": report.errors().count(),
+        "warnings": report.warnings().count(),
+        "diagnostics": report.diagnostics,
+    })
+}
+
+/// Builds the JSON output for the results of validating one or more files. The structure is the same
+/// however many files were validated, and is stable; fields may be added but are never renamed or removed:
+///
+/// ```json
+/// {
+///   "valid": false,
+///   "summary": { "files": 1, "valid": 0, "invalid": 1, "failed": 0 },
+///   "files": [
+///     {
+///       "file": "ibis.json",
+///       "valid": false,
+///       "errors": 1,
+///       "warnings": 0,
+///       "diagnostics": [
+///         {
+///           "pointer": "/pages/foo/entryType", "rule": "enum", "message": "...", "severity": "error",
+///           "location": { "line": 12, "column": 20, "end_line": 12, "end_column": 30 },
+///           "suggestions": ["function"]
+///         }
+///       ]
+///     }
+///   ]
+/// }
+/// ```
+///
+/// A file that couldn't be validated is `{ "file": "...", "valid": false, "error": "..." }` instead.
+/// `location` is left out of a diagnostic if its value couldn't be found in the file, and `suggestions` is
+/// left out if there aren't any.
+fn to_json(results: &[FileResult]) -> Value {
+    let files: Vec<Value> = results.iter().map(|result| match &result.report {
+        Ok(report) => file_json(&result.file, report),
+        Err(err) => json!({ "file": result.file, "valid": false, "error": format!("{:#}", err) }),
+    }).collect();
+
+    let valid = results.iter().filter(|r| r.is_valid()).count();
+    let failed = results.iter().filter(|r| r.report.is_err()).count();
+    json!({
+        "valid": valid == results.len(),
+        "summary": {
+            "files": results.len(),
+            "valid": valid,
+            "invalid": results.len() - valid - failed,
+            "failed": failed,
+        },
+        "files": files,
+    })
+}
+
+/// Builds a SARIF result pointing at a file, with the JSON pointer stored as a logical location. The
+/// region of the file is included if the location of the value is known.
+fn sarif_result(file: &str, rule: &str, level: &str, message: &str, pointer: &str, location: Option<&Location>) -> Value {
+    let mut physical_location = json!({ "artifactLocation": { "uri": file } });
+    if let Some(location) = location {
+        physical_location["region"] = json!({
+            "startLine": location.line,
+            "startColumn": location.column,
+            "endLine": location.end_line,
+            "endColumn": location.end_column,
+        });
+    }
+    json!({
+        "ruleId": rule,
+        "level": level,
+        "message": { "text": message },
+        "locations": [{
+            "physicalLocation": physical_location,
+            "logicalLocations": [{
+                "fullyQualifiedName": pointer,
+                "kind": "object",
+            }],
+        }],
+    })
+}
+
+/// Builds a SARIF 2.1.0 log for the results of validating one or more files, for code review tools that
+/// show annotations. Files that couldn't be validated are reported with the `unreadable-file` rule.
+fn to_sarif(results: &[FileResult]) -> Value {
+    let mut rules: BTreeSet<&str> = BTreeSet::new();
+    let mut sarif_results: Vec<Value> = Vec::new();
+
+    for result in results {
+        match &result.report {
+            Ok(report) => for diagnostic in &report.diagnostics {
+                let level = match diagnostic.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                };
+                rules.insert(&diagnostic.rule);
+                let message = if diagnostic.suggestions.is_empty() { diagnostic.message.clone() }
+                    else { format!("{}. Did you mean {}?", diagnostic.message, suggestion_list(&diagnostic.suggestions)) };
+                sarif_results.push(sarif_result(&result.file, &diagnostic.rule, level, &message, &diagnostic.pointer, diagnostic.location.as_ref()));
+            },
+            Err(err) => {
+                rules.insert("unreadable-file");
+                sarif_results.push(sarif_result(&result.file, "unreadable-file", "error", &format!("{:#}", err), "", None));
+            }
+        }
+    }
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "ibis",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/samwightt/ibis",
+                    "rules": rules.iter().map(|id| json!({ "id": id })).collect::<Vec<Value>>(),
+                },
+            },
+            "results": sarif_results,
+        }],
+    })
+}
+
+/// Prints a report for a single file in the given format. Only the text format differs from printing the
+/// results of validating many files.
+pub fn print_report(format: Format, file: &str, report: &ValidationReport) {
+    match format {
+        Format::Text => print_text(file, report),
+        _ => print_results(format, &[FileResult { file: String::from(file), report: Ok(report.clone()) }]),
+    }
+}
+
+/// Prints the results of validating many files in the given format.
+pub fn print_results(format: Format, results: &[FileResult]) {
+    match format {
+        Format::Text => print_text_many(results),
+        Format::Json => println!("{:#}", to_json(results)),
+        Format::Sarif => println!("{:#}", to_sarif(results)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    /// One file with an error that has a location and a suggestion, and a warning that has neither, plus
+    /// a file that couldn't be validated.
+    fn results() -> Vec<FileResult> {
+        let report = ValidationReport {
+            diagnostics: vec![
+                Diagnostic {
+                    location: Some(Location { line: 3, column: 20, end_line: 3, end_column: 30 }),
+                    suggestions: vec![String::from("function")],
+                    ..Diagnostic::error("/pages/a/entryType", "enum", "Enum conditions are not met")
+                },
+                Diagnostic::warning("/pages/b", "unreachable-page", "Page `b` can't be reached from the sidebar."),
+            ],
+        };
+        vec![
+            FileResult { file: String::from("a/ibis.json"), report: Ok(report) },
+            FileResult { file: String::from("b/ibis.json"), report: Err(anyhow!("Could not open file.")) },
+        ]
+    }
+
+    #[test]
+    fn prints_the_json_envelope() {
+        assert_eq!(to_json(&results()), json!({
+            "valid": false,
+            "summary": { "files": 2, "valid": 0, "invalid": 1, "failed": 1 },
+            "files": [
+                {
+                    "file": "a/ibis.json",
+                    "valid": false,
+                    "errors": 1,
+                    "warnings": 1,
+                    "diagnostics": [
+                        {
+                            "pointer": "/pages/a/entryType", "rule": "enum", "message": "Enum conditions are not met", "severity": "error",
+                            "location": { "line": 3, "column": 20, "end_line": 3, "end_column": 30 },
+                            "suggestions": ["function"],
+                        },
+                        { "pointer": "/pages/b", "rule": "unreachable-page", "message": "Page `b` can't be reached from the sidebar.", "severity": "warning" },
+                    ],
+                },
+                { "file": "b/ibis.json", "valid": false, "error": "Could not open file." },
+            ],
+        }));
+        assert_eq!(to_json(&[]), json!({ "valid": true, "summary": { "files": 0, "valid": 0, "invalid": 0, "failed": 0 }, "files": [] }));
+    }
+
+    #[test]
+    fn prints_sarif_results() {
+        let sarif = to_sarif(&results());
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(sarif["runs"][0]["tool"]["driver"]["rules"], json!([{ "id": "enum" }, { "id": "unreachable-page" }, { "id": "unreadable-file" }]));
+        assert_eq!(sarif["runs"][0]["results"], json!([
+            {
+                "ruleId": "enum",
+                "level": "error",
+                "message": { "text": "Enum conditions are not met. Did you mean `function`?" },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": "a/ibis.json" },
+                        "region": { "startLine": 3, "startColumn": 20, "endLine": 3, "endColumn": 30 },
+                    },
+                    "logicalLocations": [{ "fullyQualifiedName": "/pages/a/entryType", "kind": "object" }],
+                }],
+            },
+            {
+                "ruleId": "unreachable-page",
+                "level": "warning",
+                "message": { "text": "Page `b` can't be reached from the sidebar." },
+                "locations": [{
+                    "physicalLocation": { "artifactLocation": { "uri": "a/ibis.json" } },
+                    "logicalLocations": [{ "fullyQualifiedName": "/pages/b", "kind": "object" }],
+                }],
+            },
+            {
+                "ruleId": "unreadable-file",
+                "level": "error",
+                "message": { "text": "Could not open file." },
+                "locations": [{
+                    "physicalLocation": { "artifactLocation": { "uri": "b/ibis.json" } },
+                    "logicalLocations": [{ "fullyQualifiedName": "", "kind": "object" }],
+                }],
+            },
+        ]));
+    }
+}
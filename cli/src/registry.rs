@@ -0,0 +1,57 @@
+mod sync;
+mod search;
+mod info;
+
+use clap::{App, ArgMatches};
+use colored::Colorize;
+use base::cache::{CacheStore, unix_time};
+use base::index::LocalIndex;
+use base::registry::RegistryConfig;
+use anyhow::{Result, anyhow};
+
+// The Clap subcommand for the registry module.
+pub fn subcommand<'a>() -> App<'a> {
+    App::new("registry")
+        .about("Commands for browsing the packages in registries.")
+        .version("0.1.0")
+        .subcommand(sync::subcommand())
+        .subcommand(search::subcommand())
+        .subcommand(info::subcommand())
+}
+
+/// Formats how long ago a Unix time was for people, ex. `3 hours ago`.
+fn format_age(time: u64) -> String {
+    let seconds = unix_time().saturating_sub(time);
+    let (amount, unit) = match seconds {
+        0..=59 => return String::from("just now"),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86399 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+    format!("{} {}{} ago", amount, unit, if amount == 1 { "" } else { "s" })
+}
+
+/// Loads the synced index of every registry, in the order they're tried in. Warns about registries that
+/// haven't been synced from their current URL, and fails if none have.
+async fn load_indexes(store: &dyn CacheStore, registries: &[RegistryConfig]) -> Result<Vec<LocalIndex>> {
+    let mut indexes = Vec::new();
+    for registry in registries {
+        match LocalIndex::load(store, registry).await? {
+            Some(index) => indexes.push(index),
+            None => eprintln!("{}", format!("The index of {} hasn't been synced from {}.", registry.name, registry.url).yellow()),
+        }
+    }
+    if indexes.is_empty() {
+        return Err(anyhow!("No registry indexes have been synced. Run `ibis registry sync` first."));
+    }
+    Ok(indexes)
+}
+
+pub async fn run(app: &ArgMatches) -> Result<()> {
+    if let Some(registry_command) = app.subcommand_matches("registry") {
+        sync::run(registry_command).await?;
+        search::run(registry_command).await?;
+        info::run(registry_command).await?;
+    }
+    Ok(())
+}
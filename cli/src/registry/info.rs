@@ -0,0 +1,57 @@
+use clap::{App, Arg, ArgMatches};
+use colored::Colorize;
+use base::cache::FsStore;
+use base::config::Config;
+use base::index::sorted_versions;
+use base::registry::resolve_registries;
+use anyhow::{Result, Context, anyhow};
+use super::{format_age, load_indexes};
+
+// The Clap subcommand for the info module.
+pub fn subcommand<'a>() -> App<'a> {
+    App::new("info")
+        .about("Shows a package's repository and versions from the synced registry indexes. Works offline.")
+        .version("0.1.0")
+        .arg(
+            Arg::with_name("name")
+                .index(1)
+                .required(true)
+                .about("The name of the package."),
+        )
+}
+
+/// Runs the info subcommand. Fails if no synced index has the package.
+pub async fn run(app: &ArgMatches) -> Result<()> {
+    if let Some(info_command) = app.subcommand_matches("info") {
+        let name = info_command.value_of("name").unwrap_or_default();
+        let store = FsStore::new();
+        let config = Config::load(&store).await.context("Could not load config.")?;
+        let registries = resolve_registries(&config.registries, info_command.value_of("registry"));
+        let indexes = load_indexes(&store, &registries).await?;
+
+        let mut found = false;
+        for index in &indexes {
+            let listing = match index.get(name) {
+                Some(listing) => listing,
+                None => continue,
+            };
+            found = true;
+            println!("{}", listing.name.blue().bold());
+            println!("  {} {} (synced {})", "Registry:".bold(), index.registry, format_age(index.synced_at));
+            if let Some(repo) = &listing.repo {
+                println!("  {} {}", "Repository:".bold(), repo);
+            }
+            let versions = sorted_versions(listing);
+            if versions.is_empty() {
+                println!("  {} none listed", "Versions:".bold());
+            }
+            else {
+                println!("  {} {}", "Versions:".bold(), versions.join(", "));
+            }
+        }
+        if !found {
+            return Err(anyhow!("No synced registry index has {}.", name));
+        }
+    }
+    Ok(())
+}
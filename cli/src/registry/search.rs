@@ -0,0 +1,44 @@
+use clap::{App, Arg, ArgMatches};
+use colored::Colorize;
+use base::cache::FsStore;
+use base::config::Config;
+use base::index::search;
+use base::registry::resolve_registries;
+use anyhow::{Result, Context};
+use super::load_indexes;
+
+// The Clap subcommand for the search module.
+pub fn subcommand<'a>() -> App<'a> {
+    App::new("search")
+        .about("Searches the synced registry indexes for packages by name. Works offline.")
+        .version("0.1.0")
+        .arg(
+            Arg::with_name("query")
+                .index(1)
+                .required(true)
+                .about("The text to look for in package names."),
+        )
+}
+
+/// Runs the search subcommand.
+pub async fn run(app: &ArgMatches) -> Result<()> {
+    if let Some(search_command) = app.subcommand_matches("search") {
+        let query = search_command.value_of("query").unwrap_or_default();
+        let store = FsStore::new();
+        let config = Config::load(&store).await.context("Could not load config.")?;
+        let registries = resolve_registries(&config.registries, search_command.value_of("registry"));
+        let indexes = load_indexes(&store, &registries).await?;
+
+        let hits = search(&indexes, query);
+        if hits.is_empty() {
+            println!("{}", format!("No packages match `{}`.", query).yellow().bold());
+            return Ok(());
+        }
+        println!("{}", format!("Found {} package(s):", hits.len()).green().bold());
+        for hit in hits {
+            let latest = hit.latest.map(|version| format!("@{}", version)).unwrap_or_default();
+            println!("  - {}{} ({})", hit.listing.name.bold(), latest, hit.registry);
+        }
+    }
+    Ok(())
+}
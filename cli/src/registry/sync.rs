@@ -0,0 +1,42 @@
+use clap::{App, ArgMatches};
+use colored::Colorize;
+use std::sync::Arc;
+use base::cache::FsStore;
+use base::config::Config;
+use base::index::sync;
+use base::registry::resolve_registries;
+use base::validators::RepoSchema;
+use anyhow::{Result, Context, anyhow};
+
+// The Clap subcommand for the sync module.
+pub fn subcommand<'a>() -> App<'a> {
+    App::new("sync")
+        .about("Downloads the index of every registry (or only --registry), so it can be searched offline.")
+        .version("0.1.0")
+}
+
+/// Runs the sync subcommand. Fails if any registry couldn't be synced.
+pub async fn run(app: &ArgMatches) -> Result<()> {
+    if let Some(sync_command) = app.subcommand_matches("sync") {
+        let store = Arc::new(FsStore::new());
+        let config = Config::load(&*store).await.context("Could not load config.")?;
+        let registries = resolve_registries(&config.registries, sync_command.value_of("registry"));
+        let schema = RepoSchema { cache: config.cache, store: store.clone(), ..RepoSchema::default() };
+
+        let mut failed = 0;
+        for registry in &registries {
+            println!("{}", format!("Syncing {}...", registry.name).blue().bold());
+            match sync(&*store, &schema, registry).await {
+                Ok(index) => println!("{}", format!("Synced {} package(s) from {}.", index.packages.len(), registry.name).green().bold()),
+                Err(err) => {
+                    eprintln!("{}{:#}", "Could not sync: ".red().bold(), err);
+                    failed += 1;
+                }
+            }
+        }
+        if failed > 0 {
+            return Err(anyhow!("{} registry index(es) could not be synced.", failed));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use clap::{App, Arg, ArgMatches};
+use colored::Colorize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use base::cache::FsStore;
+use base::config::Config;
+use base::install::InstallStatus;
+use base::project::sync;
+use base::registry::resolve_registries;
+use base::validators::PackageSchema;
+use anyhow::{Result, Context};
+
+// The Clap subcommand for the sync module.
+pub fn subcommand<'a>() -> App<'a> {
+    App::new("sync")
+        .about("Installs the packages listed in the project's ibis.deps and records exactly what was installed in ibis.lock.")
+        .version("0.1.0")
+        .arg(
+            Arg::with_name("locked")
+                .long("locked")
+                .about("Installs exactly what ibis.lock lists. Fails if ibis.lock is missing or out of date instead of updating it."),
+        )
+        .arg(
+            Arg::with_name("dir")
+                .long("dir")
+                .takes_value(true)
+                .default_value(".")
+                .about("The project directory, which has the ibis.deps file."),
+        )
+}
+
+/// Runs the sync subcommand.
+pub async fn run(app: &ArgMatches) -> Result<()> {
+    if let Some(sync_command) = app.subcommand_matches("sync") {
+        let dir = PathBuf::from(sync_command.value_of("dir").unwrap_or("."));
+        let locked = sync_command.is_present("locked");
+        let store = Arc::new(FsStore::new());
+        let config = Config::load(&*store).await.context("Could not load config.")?;
+        let registries = resolve_registries(&config.registries, sync_command.value_of("registry"));
+        let schema = || PackageSchema { offline: false, lint: config.lint.clone(), cache: config.cache.clone(), store: store.clone() };
+
+        println!("{}", format!("Syncing the packages in {}...", dir.join(base::constants::DEPS_PATH).display()).blue().bold());
+        let synced = sync(&dir, &*store, schema, &registries, locked).await?;
+        for synced in &synced {
+            let package = &synced.package;
+            let status = match synced.status {
+                InstallStatus::Installed => "installed",
+                InstallStatus::Replaced => "reinstalled",
+                InstallStatus::AlreadyInstalled => "up to date",
+            };
+            println!("  - {}@{} ({}): {}", package.name.bold(), package.version, &package.commit[..package.commit.len().min(7)], status);
+        }
+        println!("{}", format!("Synced {} package(s).", synced.len()).green().bold());
+    }
+    Ok(())
+}
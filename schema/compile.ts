@@ -3,6 +3,12 @@ import { resolve } from "path";
 
 import * as TJS from "typescript-json-schema";
 
+/**
+ * The revision of the generated schemas. Bump this whenever schema.ts or repo.ts change. Ibis compares it
+ * against the revision of the schemas built into the CLI to decide whether a cached schema is newer.
+ */
+const REVISION = 6;
+
 const settings: TJS.PartialArgs = {
   required: true,
 };
@@ -16,7 +22,7 @@ const program = TJS.getProgramFromFiles(
   compilerOptions
 );
 
-const schema = TJS.generateSchema(program, "RootType", settings);
+const schema = { ...TJS.generateSchema(program, "RootType", settings), revision: REVISION };
 
 fs.writeFile("../schema.json", JSON.stringify(schema, null, 4), (err) => {
   if (err) console.log("There was an error creating the file.");
@@ -33,7 +39,7 @@ const otherProgram = TJS.getProgramFromFiles(
   compilerOptions
 );
 
-const repo = TJS.generateSchema(otherProgram, "RootType", settings);
+const repo = { ...TJS.generateSchema(otherProgram, "RootType", settings), revision: REVISION };
 
 fs.writeFile("../repo.json", JSON.stringify(repo, null, 4), (err) => {
   if (err) console.log("There was an error creating the file.");
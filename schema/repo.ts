@@ -1,10 +1,21 @@
 interface VersionInterface {
+  /**
+   * The version of the package. Semantic versions are recommended so version ranges can match them.
+   */
   version: string
+  /**
+   * The git repository to install this version from, optionally followed by `#` and a tag, branch, or commit.
+   * Without one, the `v<version>` or `<version>` tag is installed, or HEAD if the repository has neither.
+   */
   url: string
 }
 
 interface PackageInterface {
   name: string
+  /**
+   * The git repository the package is developed in.
+   */
+  repo?: string
   versions: VersionInterface[]
 }
 
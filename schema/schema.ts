@@ -3,7 +3,8 @@
  */
 type EntryTypes =
   | "annotation"
-  | "attibute"
+  | "attibute" // Misspelled, kept so existing packages stay valid. Use "attribute" instead.
+  | "attribute"
   | "binding"
   | "builtin"
   | "callback"
@@ -122,6 +123,17 @@ interface PageTable {
 type Sidebar = string[];
 
 interface RootType {
+  /**
+   * A URL to the version of the Ibis schema this package targets. May be used instead of `schemaVersion`.
+   */
+  $schema?: string;
+  /**
+   * The version of the Ibis schema this package targets. Packages that don't set this or `$schema`
+   * are treated as targeting version 1.
+   *
+   * @TJS-type integer
+   */
+  schemaVersion?: number;
   /**
    * The name of the package. May contain only alphabetic characters, slashes, dashes, and underscores.
    */